@@ -2,7 +2,8 @@ use p256::elliptic_curve::rand_core::OsRng;
 use pokemmo::{
     codec::Login,
     context::WithContext,
-    message::{ClientHello, ClientReady, ServerHello},
+    handshake::{self, ClientConfig},
+    identity::TrustStore,
 };
 use std::net::TcpStream;
 
@@ -11,31 +12,35 @@ const SECONDARY_OBFUSCATION_VALUE: i64 = -4214651440992349575;
 const LOCAL_SERVER: &str = "127.0.0.1:2106";
 const REMOTE_SERVER: &str = "loginserver.pokemmo.com:2106";
 
+// Identity key of `examples/server.rs` (SEC1 uncompressed); pin the real server key instead
+// when connecting to the remote server.
+const SERVER_IDENTITY_KEY: [u8; 65] = [
+    0x04, 0x94, 0xd1, 0xbf, 0x45, 0xa8, 0xcc, 0x04, 0x32, 0x28, 0x4d, 0x5d,
+    0x79, 0x4b, 0xc4, 0xf4, 0x99, 0xa5, 0xbc, 0xd2, 0xdf, 0x95, 0x18, 0xc2,
+    0x89, 0xb1, 0xf7, 0x7d, 0x1e, 0x2d, 0x04, 0xc3, 0x5b, 0x8d, 0x37, 0x9d,
+    0x99, 0xff, 0xba, 0xed, 0xfd, 0x03, 0xf8, 0x72, 0x90, 0xf2, 0xef, 0x59,
+    0x50, 0x5c, 0x81, 0x37, 0x8a, 0xf2, 0x62, 0x67, 0x19, 0x42, 0xf6, 0x5f,
+    0x7d, 0x45, 0x5e, 0x0f, 0x73,
+];
+
 pub fn main() {
-    let mut stream = TcpStream::connect(LOCAL_SERVER)
+    let stream = TcpStream::connect(LOCAL_SERVER)
         .or_else(|_| TcpStream::connect(REMOTE_SERVER))
         .unwrap()
         .with_logger()
         .with_context::<Login>();
 
-    let client_hello = ClientHello::new(
-        // very bad but simplest way to get a "random" value without adding extra dependencies
-        (&() as *const () as usize) as i64,
-        std::time::SystemTime::now(),
+    let server_identity_key =
+        p256::ecdsa::VerifyingKey::from_sec1_bytes(&SERVER_IDENTITY_KEY).unwrap();
+    let mut config = ClientConfig::new(
         PRIMARY_OBFUSCATION_VALUE,
         SECONDARY_OBFUSCATION_VALUE,
-    )
-    .unwrap();
-
-    stream.write_message(client_hello).unwrap();
-
-    stream.read_message::<ServerHello>().unwrap();
-
-    let client_secret_key = p256::SecretKey::random(&mut OsRng);
-    let client_public_key = client_secret_key.public_key();
-    let client_ready = ClientReady::new(client_public_key.clone());
+        TrustStore::new().with_key(server_identity_key),
+        OsRng,
+    );
 
-    stream.write_message(client_ready).unwrap();
+    let stream = handshake::client(stream, &mut config).unwrap();
+    stream.info("Switched to secure framing");
 }
 
 use std::io::{self, Read, Write};
@@ -54,6 +59,7 @@ pub trait Logged {
 }
 
 impl<T: Read + Write> Logger<T> {
+    #[allow(clippy::print_with_newline)]
     fn hexdump(prefix: &str, buf: &[u8]) {
         print!("{} ({} bytes):\n", prefix, buf.len());
         for (i, chunk) in buf.chunks(16).enumerate() {
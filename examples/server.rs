@@ -1,18 +1,46 @@
 use p256::elliptic_curve::rand_core::OsRng;
-use p256::ecdsa::{signature::Signer, SigningKey};
+use p256::ecdsa::SigningKey;
 use pokemmo::{
     codec::Login,
     context::WithContext,
-    message::{Checksum, ClientHello, ClientReady, ServerHello},
+    handshake::{self, ServerConfig},
+    identity::ServerIdentity,
+    message::Checksum,
+    replay::InMemoryReplayCache,
 };
 use std::net::TcpListener;
 use std::io::Write;
+use std::sync::Arc;
+use std::time::Duration;
 
 const PRIMARY_OBFUSCATION_VALUE: i64 = 3214621489648854472;
 const SECONDARY_OBFUSCATION_VALUE: i64 = -4214651440992349575;
 const BIND_ADDR: &str = "127.0.0.1:2106";
 
+// Example identity key; its public key is pinned by `examples/client.rs`.
+const IDENTITY_SECRET_KEY: [u8; 32] = [
+    0xcb, 0x67, 0x3a, 0x3d, 0x2a, 0x2c, 0xd5, 0xdb, 0xe5, 0xee, 0xda, 0x0e,
+    0x9a, 0x21, 0xf5, 0xa3, 0x12, 0x87, 0x75, 0x0b, 0x0d, 0x0f, 0xbd, 0xfe,
+    0xe4, 0x4e, 0xee, 0xeb, 0x4e, 0xd6, 0x2e, 0x1a,
+];
+
 pub fn main() {
+    // Long-term identity key; a real server loads this from storage so clients can pin it
+    let identity = ServerIdentity::new(SigningKey::from_slice(&IDENTITY_SECRET_KEY).unwrap());
+
+    // Choose a simple checksum policy
+    let mut config = ServerConfig::new(
+        PRIMARY_OBFUSCATION_VALUE,
+        SECONDARY_OBFUSCATION_VALUE,
+        identity,
+        Checksum::None,
+        OsRng,
+    )
+    .with_replay_cache(Arc::new(InMemoryReplayCache::new(
+        10_000,
+        Duration::from_secs(60),
+    )));
+
     let listener = TcpListener::bind(BIND_ADDR).expect("Failed to bind server socket");
     println!("Server listening on {}", BIND_ADDR);
 
@@ -20,52 +48,12 @@ pub fn main() {
     let (stream, addr) = listener.accept().expect("Failed to accept connection");
     println!("Accepted connection from {}", addr);
 
-    let mut stream = stream.with_context::<Login>();
-
-    // Read the initial ClientHello
-    let client_hello: ClientHello = stream
-        .read_message::<ClientHello>()
-        .expect("Failed to read ClientHello");
-
-    // Optionally de-obfuscate and log
-    let integrity = client_hello.integrity(PRIMARY_OBFUSCATION_VALUE);
-    let timestamp = client_hello
-        .timestamp(PRIMARY_OBFUSCATION_VALUE, SECONDARY_OBFUSCATION_VALUE)
-        .expect("Failed to decode client timestamp");
-    println!(
-        "ClientHello: integrity={}, timestamp={:?}",
-        integrity, timestamp
-    );
-
-    // Prepare a server key and signature
-    let signing_key = SigningKey::random(&mut OsRng);
-    let server_public_key: p256::PublicKey = signing_key.verifying_key().into();
-
-    // Sign the server's public key bytes for demonstration
-    let pubkey_bytes = server_public_key.to_sec1_bytes();
-    let signature = signing_key.sign(&pubkey_bytes);
-
-    // Build ServerHello; choose a simple checksum policy
-    let server_hello = ServerHello::new(server_public_key, signature, Checksum::None);
-
-    // Send ServerHello back to the client
-    stream
-        .write_message(server_hello)
-        .expect("Failed to write ServerHello");
-    println!("Sent ServerHello");
-
-    // Read ClientReady from the client
-    let client_ready: ClientReady = stream
-        .read_message::<ClientReady>()
-        .expect("Failed to read ClientReady");
+    let stream = stream.with_context::<Login>();
 
-    let client_pub = client_ready
-        .public_key()
-        .expect("Failed to parse client public key");
-    println!(
-        "ClientReady received: client pubkey (SEC1) size={} bytes",
-        client_pub.to_sec1_bytes().len()
-    );
+    // Validate ClientHello, reply with a signed ServerHello and accept ClientReady
+    let mut stream =
+        handshake::server(stream, &mut config).expect("Failed to complete handshake");
+    println!("Switched to secure framing");
 
     // Example complete; close connection
     let _ = stream.flush();
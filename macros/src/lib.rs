@@ -7,21 +7,165 @@ const MAX_PREFIXED_SIZE: usize = 10_485_760; // 10 MB
 
 /// Derives serialization and deserialization for message payload types.
 ///
-/// Implements the `Message` trait, automatically generating `serialize()` and `deserialize()` methods.
-/// All fields are serialized in little-endian byte order. Variable-length fields must be annotated.
+/// Implements the `Message` trait, automatically generating `serialize_into()`, `encoded_len()`
+/// and `deserialize_from()` methods.
+/// Numbers are serialized in little-endian byte order unless a field selects another
+/// [number format](#number-formats). Variable-length fields must be annotated.
+///
+/// Structs must have named fields. Enums are tagged unions: see [Enums](#enums).
+///
+/// Types may be generic: see [Generics](#generics). Lifetime parameters allow
+/// [borrowed fields](#borrowed-fields) that point into the input instead of copying it.
 ///
 /// ## Supported Types
 ///
 /// - **Integer types**: `i8`, `i16`, `i32`, `i64`, `u8`, `u16`, `u32`, `u64`
 ///   - Serialized as little-endian bytes.
+/// - **Floating-point types**: `f32`, `f64`
+///   - Serialized as little-endian IEEE 754 bytes.
+/// - **bool**: A single byte, `0` or `1`; any other value is rejected on decode.
+/// - **Arrays** `[T; N]` of any supported type: `N` elements in order, without a prefix.
+///   - `[u8; N]` is copied as raw bytes, e.g. for hashes and tokens.
 /// - **Vec<u8>**: Requires `#[prefixed(T)]` where `T` is an integer type for the length prefix.
 ///   - Format: `[length: T LE, data...]`
 /// - **String**: Requires `#[prefixed(T)]` attribute (similar to Vec<u8>).
+///   - Encoded as UTF-8 by default, or UTF-16LE with `utf16`; decoding validates the text.
+/// - **Vec<T>** where `T: Field`: Requires `#[count(T)]` where `T` is an integer type for
+///   the element count.
+///   - Format: `[count: T LE, element...]`, each element encoded inline.
+/// - **Other types**: Any other field type must implement `Field`, which every `Message` does.
+///   - Encoded via `Field::encode()` and `Field::decode()`; nested messages are encoded
+///     inline, without a prefix.
+///   - Types of other crates can use `#[with(path)]` instead.
+/// - **Option<T>** of any supported type: Requires `#[optional(T)]` or `#[present_if(expr)]`.
+///   - `None` occupies no bytes; `Some` is encoded like a `T` field with the same attributes.
+/// - **&'a [u8]** and **&'a str**: Borrowed forms of `Vec<u8>` and UTF-8 `String`, with the
+///   same attributes and wire format.
 ///
 /// ## Attributes
 ///
 /// - `#[prefixed(T)]`: Marks a `Vec` or `String` field with a length prefix type.
 ///   - Example: `#[prefixed(i16)]` prefixes the field with a 2-byte i16 length.
+/// - `#[prefixed(T, format)]`: Selects the [number format](#number-formats) of the prefix:
+///   `be`, `varint` or `zigzag`.
+/// - `#[prefixed(T, encoding, unit)]`: On `String` fields, optionally selects the encoding
+///   (`utf8` or `utf16`) and what the prefix counts (`bytes` or `chars`).
+///   - `chars` counts Unicode scalar values for UTF-8 and code units for UTF-16 (Java's
+///     `String.length()`).
+///   - Defaults: `utf8` counts `bytes`, `utf16` counts `chars`.
+///   - Example: `#[prefixed(i16, utf16)]` prefixes UTF-16LE text with its i16 code unit count.
+/// - `#[count(T)]`: Marks a `Vec` of messages with an element count prefix type.
+///   - Example: `#[count(i16)]` prefixes the elements with a 2-byte i16 element count.
+///   - `#[count(T, format)]` selects the number format of the count.
+/// - `#[be]`, `#[varint]`, `#[zigzag]`: Select the [number format](#number-formats) of a
+///   number field.
+/// - `#[with(path)]`: Encodes the field with the functions `path::encode(&value, writer)` and
+///   `path::decode(rdr)` instead of its `Field` implementation, e.g. for types of other crates.
+/// - `#[remainder]`: Marks the last field, a `Vec<u8>`, as holding all bytes left after the
+///   preceding fields, without a prefix.
+/// - `#[message(crate = path)]`: On the struct or enum, sets the path of the pokemmo crate:
+///   see [Crate Path](#crate-path).
+/// - `#[strict]`: On the struct or enum, makes `deserialize()` reject bytes left over after
+///   the last field. `deserialize_from()` is unaffected, so strict messages can still be
+///   nested.
+/// - `#[optional(T)]`: Precedes an `Option` field with a presence flag of integer or `bool`
+///   type `T`, `1` when present and `0` when absent.
+///   - Example: `#[optional(u8)]` writes a 1-byte flag, then the value if present.
+/// - `#[present_if(expr)]`: Makes an `Option` field present only when `expr` is `true`,
+///   without a flag of its own.
+///   - `expr` may refer to earlier fields by name. Numbers and bools are bound by value,
+///     other fields by reference.
+///   - Example: `#[present_if(flags & 0x1 != 0)]` decodes the field only if bit 0 of the
+///     earlier `flags` field is set.
+///
+/// ## Borrowed Fields
+///
+/// The generated impl is `Message<'de>`, where `'de` is the lifetime of the input and must
+/// outlive the type's lifetimes. `&'a [u8]` and `&'a str` fields point into the input, so
+/// decoding them copies nothing; `&str` is still validated as UTF-8. Nested borrowed messages
+/// and the `data: &'a [u8]` of a `#[fallback]` variant work the same way.
+///
+/// ```ignore
+/// #[derive(Message)]
+/// pub struct Chat<'a> {
+///     #[prefixed(u8)]
+///     channel: &'a str,
+///     #[remainder]
+///     text: &'a [u8],
+/// }
+///
+/// let chat = Chat::deserialize(&frame)?; // borrows `frame`
+/// ```
+///
+/// Types without lifetimes implement `Message<'de>` for every `'de`, i.e. `MessageOwned`.
+///
+/// ## Generics
+///
+/// Lifetime, type and const parameters and the where clause are carried through to the
+/// generated impl. Each type parameter `T` is additionally bounded by `T: Field<'de>`, so it
+/// can be used as a field, in a `#[count]` vector or in an `Option`; declare any other bound
+/// on the type itself.
+///
+/// ```ignore
+/// #[derive(Message)]
+/// pub struct Paged<T> {
+///     page: u16,
+///     #[count(u8)]
+///     items: Vec<T>,
+/// }
+/// ```
+///
+/// ## Crate Path
+///
+/// The generated impl refers to `Message`, `Field` and `ByteCounter` through the `pokemmo`
+/// crate, so messages can be declared in any crate depending on it. If pokemmo is reachable
+/// under another path, e.g. renamed in `Cargo.toml` or re-exported, pass it as
+/// `#[message(crate = path)]` on the type.
+///
+/// ```ignore
+/// #[derive(Message)]
+/// #[message(crate = protocol::pokemmo)]
+/// pub struct Ping {
+///     nonce: u32,
+/// }
+/// ```
+///
+/// ## Number Formats
+///
+/// Integer fields, and arrays or `Option`s of them, can select their wire format:
+/// - `#[be]`: Big-endian bytes; also applies to `f32` and `f64`.
+/// - `#[varint]`: Unsigned LEB128, 7 bits per byte with the high bit set on all but the last
+///   byte. Signed values are encoded as their two's complement bits, so negative values take
+///   the maximum length.
+/// - `#[zigzag]`: On signed integers, maps values of small magnitude to small unsigned values
+///   (`0, -1, 1, -2, ...` to `0, 1, 2, 3, ...`) before LEB128 encoding; implies `#[varint]`.
+///
+/// Length prefixes, element counts and enum tags take the same formats as options, e.g.
+/// `#[prefixed(u32, varint)]`, `#[count(u16, be)]` or `#[tag(u16, be)]`.
+///
+/// ## Enums
+///
+/// An enum with a `#[tag(T)]` attribute, where `T` is an integer type, is encoded as the
+/// discriminant of its variant as a `T`, followed by the variant's fields like a struct's.
+/// - Every variant needs an explicit discriminant; enums with fields also need a
+///   `#[repr(T)]` for Rust to accept them.
+/// - Tuple, struct and unit variants are supported, and fields take the same attributes.
+/// - An optional `#[fallback]` variant with named fields `tag: T` and `data: Vec<u8>` (or
+///   `&'a [u8]`) captures any other tag along with the rest of the input, like the `Unknown` variant of
+///   `#[codec]`. Without it, unknown tags are rejected.
+///
+/// ```ignore
+/// #[derive(Message)]
+/// #[repr(u8)]
+/// #[tag(u8)]
+/// pub enum LoginResult {
+///     Success { session: u64 } = 0x01,
+///     Banned(#[prefixed(i16, utf16)] String) = 0x02,
+///     Maintenance = 0x03,
+///     #[fallback]
+///     Other { tag: u8, data: Vec<u8> },
+/// }
+/// ```
 ///
 /// ## Examples
 ///
@@ -43,146 +187,1505 @@ const MAX_PREFIXED_SIZE: usize = 10_485_760; // 10 MB
 ///
 /// ## Errors
 ///
+/// The generated `serialize_into()` method, and so `serialize()`, returns an error if:
+/// - A prefixed length or element count does not fit its prefix type.
+/// - A `#[present_if]` field is `Some` while its condition is `false`, or `None` while it is
+///   `true`.
+///
 /// The generated `deserialize()` method returns an error if:
 /// - The input data is truncated (insufficient bytes).
-/// - A prefixed length exceeds `MAX_PREFIXED_SIZE` (10 MB) to prevent DoS attacks.
+/// - A prefixed length or element count exceeds `MAX_PREFIXED_SIZE` (10 MB) to prevent DoS attacks.
+/// - A `String` field contains invalid UTF-8 or UTF-16.
+/// - A `bool` field or an integer `#[optional]` flag is neither `0` nor `1`.
+/// - A `#[varint]` or `#[zigzag]` value does not fit its type.
+/// - Bytes are left over after the last field of a `#[strict]` message.
+/// - An enum tag matches no variant and there is no `#[fallback]` variant.
 ///
-/// ## Panic
+/// ## Compile Errors
 ///
-/// The macro panics at compile time if:
-/// - The struct contains tuple variants or unit variants.
-/// - A `Vec` or `String` field lacks a `#[prefixed(T)]` attribute.
-#[proc_macro_derive(Message, attributes(prefixed))]
+/// The macro reports a compile error, spanned at the offending tokens, if:
+/// - The type is a tuple struct, a unit struct or a union.
+/// - A `Vec`, `String` or `Option` field lacks the attribute describing its encoding.
+/// - A prefix, count, tag or flag type is not an integer type.
+/// - An attribute does not apply to the field's type, or conflicts with another attribute.
+/// - A field type is unsupported: references other than `&[u8]` and `&str`, tuples, slices,
+///   pointer-sized or 128-bit integers and `char`.
+/// - A `&str` field is UTF-16, which cannot be borrowed.
+/// - `#[message]` has an option other than `crate = path`, or `#[strict]` has arguments.
+#[proc_macro_derive(Message, attributes(message, prefixed, count, optional, present_if, tag, fallback, be, varint, zigzag, remainder, strict, with))]
 pub fn derive_message(input: TokenStream) -> TokenStream {
     let input = parse_macro_input!(input as DeriveInput);
     let name = &input.ident;
 
-    let fields = match &input.data {
+    let mut strict = false;
+    for attr in input.attrs.iter().filter(|attr| attr.path().is_ident("strict")) {
+        if !matches!(attr.meta, syn::Meta::Path(_)) {
+            return syn::Error::new_spanned(&attr.meta, "#[strict] takes no arguments")
+                .to_compile_error()
+                .into();
+        }
+        strict = true;
+    }
+    let deserialize = strict_deserialize(name, strict);
+
+    let mut krate = default_crate_path();
+    for attr in input.attrs.iter().filter(|attr| attr.path().is_ident("message")) {
+        if let Err(err) = attr.parse_nested_meta(|meta| parse_crate_option(meta, &mut krate)) {
+            return err.to_compile_error().into();
+        }
+    }
+
+    let expanded = match &input.data {
         Data::Struct(data) => match &data.fields {
-            Fields::Named(fields) => &fields.named,
-            _ => panic!("Message can only be derived for structs with named fields"),
+            Fields::Named(_) => message_struct(&input, &data.fields, &deserialize),
+            _ => Err(syn::Error::new_spanned(
+                name,
+                "Message can only be derived for structs with named fields",
+            )),
         },
-        _ => panic!("Message can only be derived for structs"),
+        Data::Enum(data) => message_enum(&input, data, &deserialize),
+        Data::Union(data) => Err(syn::Error::new_spanned(
+            data.union_token,
+            "Message can only be derived for structs and enums",
+        )),
     };
 
+    match expanded {
+        Ok(expanded) => TokenStream::from(with_crate(&krate, expanded)),
+        Err(err) => err.to_compile_error().into(),
+    }
+}
+
+/// The path of the pokemmo crate unless overridden with `crate = path`.
+fn default_crate_path() -> syn::Path {
+    syn::parse_quote! { ::pokemmo }
+}
+
+/// Parses the `crate = path` option of `#[message(...)]` and `#[codec(...)]`, the only option
+/// either takes.
+fn parse_crate_option(meta: syn::meta::ParseNestedMeta, krate: &mut syn::Path) -> syn::Result<()> {
+    if meta.path.is_ident("crate") {
+        *krate = meta.value()?.parse()?;
+        Ok(())
+    } else {
+        Err(meta.error("Unsupported option; expected `crate = path`"))
+    }
+}
+
+/// Wraps generated impls in an anonymous constant importing the pokemmo crate from `krate` as
+/// `__pokemmo`, the root of every path the generated code refers to.
+///
+/// Scoping the import this way keeps it out of the caller's namespace, so the impls compile
+/// wherever the type is declared: inside pokemmo, in a downstream crate, or behind a re-export.
+fn with_crate(krate: &syn::Path, impls: proc_macro2::TokenStream) -> proc_macro2::TokenStream {
+    quote! {
+        const _: () = {
+            use #krate as __pokemmo;
+            #impls
+        };
+    }
+}
+
+/// Generates a `deserialize()` override rejecting leftover bytes if the type is `#[strict]`.
+///
+/// Only the outermost message can tell leftover bytes from the data of the messages that
+/// follow it, so `deserialize_from()` stays lenient.
+fn strict_deserialize(name: &syn::Ident, strict: bool) -> proc_macro2::TokenStream {
+    if !strict {
+        return quote! {};
+    }
+    quote! {
+        fn deserialize(__data: &'__de [u8]) -> ::std::io::Result<Self> {
+            let mut __rdr = ::std::io::Cursor::new(__data);
+            let __message = Self::deserialize_from(&mut __rdr)?;
+            if (__rdr.position() as usize) < __data.len() {
+                return ::core::result::Result::Err(::std::io::Error::new(
+                    ::std::io::ErrorKind::InvalidData,
+                    ::core::concat!("Trailing bytes after ", ::core::stringify!(#name))
+                ));
+            }
+            ::core::result::Result::Ok(__message)
+        }
+    }
+}
+
+/// Generates the header of a `Trait<'__de>` impl for the type of `input`, e.g.
+/// `impl<'__de: 'a, 'a, T> Message<'__de> for Page<'a, T> where T: Field<'__de>`.
+///
+/// `'__de` is the lifetime of the input being decoded, so borrowed fields may only point into
+/// inputs that outlive the type's own lifetimes. The type's generics and where clause are
+/// carried through, extended with `bounds`.
+fn decode_impl(
+    input: &DeriveInput,
+    trait_path: proc_macro2::TokenStream,
+    bounds: Vec<syn::WherePredicate>,
+) -> proc_macro2::TokenStream {
+    let name = &input.ident;
+    let mut generics = input.generics.clone();
+    let mut input_lifetime = syn::LifetimeParam::new(syn::Lifetime::new("'__de", proc_macro2::Span::call_site()));
+    input_lifetime
+        .bounds
+        .extend(input.generics.lifetimes().map(|param| param.lifetime.clone()));
+    generics.params.insert(0, syn::GenericParam::Lifetime(input_lifetime));
+    generics.make_where_clause().predicates.extend(bounds);
+
+    let (impl_generics, _, where_clause) = generics.split_for_impl();
+    let (_, ty_generics, _) = input.generics.split_for_impl();
+    quote! {
+        impl #impl_generics #trait_path<'__de> for #name #ty_generics #where_clause
+    }
+}
+
+/// Bounds every type parameter of a message type by `Field<'__de>`, which covers fields of the
+/// parameter's type as well as `#[count]` vectors and options of it.
+fn field_bounds(generics: &syn::Generics) -> Vec<syn::WherePredicate> {
+    generics
+        .type_params()
+        .map(|param| {
+            let ident = &param.ident;
+            syn::parse_quote! { #ident: __pokemmo::message::Field<'__de> }
+        })
+        .collect()
+}
+
+/// Generates the `Message` impl of a struct with named fields.
+fn message_struct(
+    input: &DeriveInput,
+    fields: &Fields,
+    deserialize: &proc_macro2::TokenStream,
+) -> syn::Result<proc_macro2::TokenStream> {
+    let header = decode_impl(input, quote! { __pokemmo::message::Message }, field_bounds(&input.generics));
+    let fields = field_bindings(fields);
+    let field_names = fields.iter().map(|(field_name, _)| field_name);
+    let (serialize_statements, encoded_lens, deserialize_statements) = fields_statements(&fields, Access::SelfField)?;
+
+    Ok(quote! {
+        #header {
+            fn serialize_into(&self, __data: &mut impl ::std::io::Write) -> ::std::io::Result<()> {
+                #(#serialize_statements)*
+                ::core::result::Result::Ok(())
+            }
+
+            fn encoded_len(&self) -> usize {
+                0 #(+ #encoded_lens)*
+            }
+
+            #deserialize
+
+            fn deserialize_from(__rdr: &mut ::std::io::Cursor<&'__de [u8]>) -> ::std::io::Result<Self> {
+                use ::std::io::Read;
+                let __data: &'__de [u8] = *__rdr.get_ref();
+                #(#deserialize_statements)*
+                ::core::result::Result::Ok(Self {
+                    #(#field_names),*
+                })
+            }
+        }
+    })
+}
+
+/// Generates the `Message` impl of a tagged-union enum.
+///
+/// Each variant is encoded as its discriminant, as a `#[tag(T)]` value, followed by its fields.
+/// A `#[fallback]` variant with `tag` and `data` fields captures unknown tags and the rest of
+/// the input.
+fn message_enum(
+    input: &DeriveInput,
+    data: &syn::DataEnum,
+    deserialize: &proc_macro2::TokenStream,
+) -> syn::Result<proc_macro2::TokenStream> {
+    let name = &input.ident;
+    let header = decode_impl(input, quote! { __pokemmo::message::Message }, field_bounds(&input.generics));
+    let tag_attr = input
+        .attrs
+        .iter()
+        .find(|attr| attr.path().is_ident("tag"))
+        .ok_or_else(|| syn::Error::new_spanned(name, "Message enums require a #[tag(type)] attribute"))?;
+    let Prefix {
+        prefix_type: tag_type,
+        format: tag_format,
+    } = tag_attr.parse_args()?;
+    let tag_ident = syn::Ident::new("tag", proc_macro2::Span::call_site());
+    let decode_tag = decode_number(&tag_type, tag_format, &tag_ident);
+
+    let mut serialize_arms = Vec::new();
+    let mut encoded_len_arms = Vec::new();
+    let mut deserialize_arms = Vec::new();
+    let mut fallback = None;
+
+    for variant in &data.variants {
+        let variant_name = &variant.ident;
+
+        if variant.attrs.iter().any(|attr| attr.path().is_ident("fallback")) {
+            if fallback.is_some() {
+                return Err(syn::Error::new_spanned(variant, "Only one variant can be #[fallback]"));
+            }
+            let field_type = |field_name: &str| match &variant.fields {
+                Fields::Named(fields) => fields
+                    .named
+                    .iter()
+                    .find(|field| field.ident.as_ref().is_some_and(|ident| ident == field_name))
+                    .map(|field| &field.ty),
+                _ => None,
+            };
+            let data_type = field_type("data");
+            if variant.fields.len() != 2
+                || field_type("tag").is_none()
+                || !data_type.is_some_and(|ty| is_byte_vec(ty) || is_byte_slice(ty))
+            {
+                return Err(syn::Error::new_spanned(
+                    variant,
+                    "The #[fallback] variant must have named fields `tag` and `data: Vec<u8>` or `data: &[u8]`",
+                ));
+            }
+            let read_rest = read_rest(data_type.is_some_and(is_byte_slice));
+
+            let encode_tag = encode_number(&tag_type, tag_format, &quote! { (*__tag) });
+            let tag_len = number_len(&tag_type, tag_format, &quote! { (*__tag) });
+            serialize_arms.push(quote! {
+                Self::#variant_name { tag: __tag, data: __rest } => {
+                    #encode_tag
+                    __data.write_all(__rest)?;
+                }
+            });
+            encoded_len_arms.push(quote! {
+                #[allow(unused_variables)]
+                Self::#variant_name { tag: __tag, data: __rest } => #tag_len + __rest.len(),
+            });
+            fallback = Some(quote! {
+                __tag => ::core::result::Result::Ok(Self::#variant_name { tag: __tag, data: #read_rest }),
+            });
+            continue;
+        }
+
+        let Some((_, discriminant)) = &variant.discriminant else {
+            return Err(syn::Error::new_spanned(
+                variant,
+                "Message enum variants require an explicit discriminant",
+            ));
+        };
+
+        let fields = field_bindings(&variant.fields);
+        let field_names: Vec<_> = fields.iter().map(|(field_name, _)| field_name).collect();
+        let (serialize_statements, encoded_lens, deserialize_statements) = fields_statements(&fields, Access::Binding)?;
+        let pattern = match &variant.fields {
+            Fields::Named(_) => quote! { Self::#variant_name { #(#field_names),* } },
+            Fields::Unnamed(_) => quote! { Self::#variant_name(#(#field_names),*) },
+            Fields::Unit => quote! { Self::#variant_name },
+        };
+        let encode_tag = encode_number(&tag_type, tag_format, &quote! { __tag });
+        let tag_len = number_len(&tag_type, tag_format, &quote! { { let __tag: #tag_type = #discriminant; __tag } });
+
+        serialize_arms.push(quote! {
+            #pattern => {
+                {
+                    let __tag: #tag_type = #discriminant;
+                    #encode_tag
+                }
+                #(#serialize_statements)*
+            }
+        });
+        encoded_len_arms.push(quote! {
+            #[allow(unused_variables)]
+            #pattern => #tag_len #(+ #encoded_lens)*,
+        });
+        deserialize_arms.push(quote! {
+            _ if __tag == #discriminant => {
+                #(#deserialize_statements)*
+                ::core::result::Result::Ok(#pattern)
+            }
+        });
+    }
+
+    let fallback = fallback.unwrap_or_else(|| {
+        quote! {
+            _ => ::core::result::Result::Err(::std::io::Error::new(
+                ::std::io::ErrorKind::InvalidData,
+                ::core::concat!("Unknown tag for ", ::core::stringify!(#name))
+            )),
+        }
+    });
+
+    Ok(quote! {
+        #header {
+            fn serialize_into(&self, __data: &mut impl ::std::io::Write) -> ::std::io::Result<()> {
+                match self {
+                    #(#serialize_arms)*
+                }
+                ::core::result::Result::Ok(())
+            }
+
+            fn encoded_len(&self) -> usize {
+                match self {
+                    #(#encoded_len_arms)*
+                }
+            }
+
+            #deserialize
+
+            fn deserialize_from(__rdr: &mut ::std::io::Cursor<&'__de [u8]>) -> ::std::io::Result<Self> {
+                use ::std::io::Read;
+                let __data: &'__de [u8] = *__rdr.get_ref();
+                let __tag: #tag_type = #decode_tag;
+                match __tag {
+                    #(#deserialize_arms)*
+                    #fallback
+                }
+            }
+        }
+    })
+}
+
+/// How generated serialize statements reach a field's value.
+#[derive(Clone, Copy)]
+enum Access {
+    /// Through `self.name`, in a struct.
+    SelfField,
+    /// Through a `name: &T` binding from matching an enum variant.
+    Binding,
+}
+
+impl Access {
+    /// Returns a place expression of the field's type.
+    fn place(self, field_name: &syn::Ident) -> proc_macro2::TokenStream {
+        match self {
+            Access::SelfField => quote! { self.#field_name },
+            Access::Binding => quote! { (*#field_name) },
+        }
+    }
+
+    /// Returns an expression borrowing the field.
+    fn reference(self, field_name: &syn::Ident) -> proc_macro2::TokenStream {
+        match self {
+            Access::SelfField => quote! { &self.#field_name },
+            Access::Binding => quote! { #field_name },
+        }
+    }
+
+    /// Returns an expression copying the field, for `Copy` types.
+    fn copy(self, field_name: &syn::Ident) -> proc_macro2::TokenStream {
+        match self {
+            Access::SelfField => quote! { self.#field_name },
+            Access::Binding => quote! { *#field_name },
+        }
+    }
+}
+
+/// Names the fields of a struct or variant; tuple fields are named `field_0`, `field_1`, ...
+fn field_bindings(fields: &Fields) -> Vec<(syn::Ident, &syn::Field)> {
+    fields
+        .iter()
+        .enumerate()
+        .map(|(index, field)| {
+            let field_name = field
+                .ident
+                .clone()
+                .unwrap_or_else(|| quote::format_ident!("field_{}", index));
+            (field_name, field)
+        })
+        .collect()
+}
+
+/// Generates the serialize statements, the encoded length expressions and the deserialize
+/// statements of a list of fields.
+///
+/// The deserialize statements bind each field to a local of the same name.
+#[allow(clippy::type_complexity)]
+fn fields_statements(
+    fields: &[(syn::Ident, &syn::Field)],
+    access: Access,
+) -> syn::Result<(Vec<proc_macro2::TokenStream>, Vec<proc_macro2::TokenStream>, Vec<proc_macro2::TokenStream>)> {
     let mut serialize_statements = Vec::new();
+    let mut encoded_lens = Vec::new();
     let mut deserialize_statements = Vec::new();
-    let mut field_names = Vec::new();
 
-    for field in fields {
-        let field_name = field.ident.as_ref().unwrap();
-        field_names.push(field_name);
+    for (index, (field_name, field)) in fields.iter().enumerate() {
         let field_type = &field.ty;
+        let place = access.place(field_name);
+        let reference = access.reference(field_name);
+
+        if let Some(attr) = field.attrs.iter().find(|attr| attr.path().is_ident("remainder")) {
+            if index + 1 != fields.len() {
+                return Err(syn::Error::new_spanned(attr, "#[remainder] must be on the last field"));
+            }
+        }
 
-        // Check for #[prefixed(type)] attribute
-        let prefixed_attr = field.attrs.iter().find(|attr| attr.path().is_ident("prefixed"));
+        // Check for #[optional(type)] and #[present_if(condition)] attributes
+        let optional_attr = field.attrs.iter().find(|attr| attr.path().is_ident("optional"));
+        let present_if_attr = field.attrs.iter().find(|attr| attr.path().is_ident("present_if"));
+
+        if optional_attr.is_none() && present_if_attr.is_none() {
+            let (serialize, encoded_len, decode) = field_statements(field_name, field_type, &field.attrs, &place)?;
+            serialize_statements.push(serialize);
+            encoded_lens.push(encoded_len);
+            deserialize_statements.push(quote! {
+                let #field_name = #decode;
+            });
+            continue;
+        }
+
+        // This is an Option<T> whose value is preceded by a flag or guarded by a condition
+        if let (Some(_), Some(present_if_attr)) = (optional_attr, present_if_attr) {
+            return Err(syn::Error::new_spanned(
+                present_if_attr,
+                "#[optional] and #[present_if] cannot be combined",
+            ));
+        }
+        let inner_type = generic_inner(field_type, "Option").ok_or_else(|| {
+            syn::Error::new_spanned(field_type, "#[optional] and #[present_if] require an Option<T> field")
+        })?;
+        let (encode, inner_len, decode) = field_statements(field_name, inner_type, &field.attrs, &quote! { (*__value) })?;
+        // The value's length, or 0 if absent
+        let value_len = quote! {
+            match #reference {
+                #[allow(unused_variables)]
+                ::core::option::Option::Some(__value) => #inner_len,
+                ::core::option::Option::None => 0,
+            }
+        };
+
+        if let Some(attr) = optional_attr {
+            let flag_type: Type = attr.parse_args()?;
+            let is_bool = type_ident(&flag_type).is_some_and(|ident| ident == "bool");
+            let is_integer = is_number(&flag_type)
+                && type_ident(&flag_type).is_some_and(|ident| ident != "f32" && ident != "f64");
+            if !is_bool && !is_integer {
+                return Err(syn::Error::new_spanned(
+                    &flag_type,
+                    "#[optional(type)] requires an integer or bool flag type",
+                ));
+            }
+
+            let (flag, present) = if is_bool {
+                (quote! { #place.is_some() }, quote! { __flag })
+            } else {
+                (
+                    quote! { #place.is_some() as #flag_type },
+                    quote! {
+                        match __flag {
+                            0 => false,
+                            1 => true,
+                            _ => return ::core::result::Result::Err(::std::io::Error::new(
+                                ::std::io::ErrorKind::InvalidData,
+                                ::core::concat!("Invalid presence flag for ", ::core::stringify!(#field_name))
+                            )),
+                        }
+                    },
+                )
+            };
+            let encode_flag = encode_value(&flag_type, NumberFormat::Le, &quote! { __flag });
+            let decode_flag = decode_value(&flag_type, NumberFormat::Le, field_name);
 
-        if let Some(attr) = prefixed_attr {
-            // This is a Vec<u8> or String with a length prefix
-            let prefix_type: Type = attr.parse_args().expect("Expected type in #[prefixed(type)]");
-            
             serialize_statements.push(quote! {
-                let size: #prefix_type = self.#field_name.len()
-                    .try_into()
-                    .map_err(|err| std::io::Error::new(std::io::ErrorKind::InvalidData, err))?;
-                data.write_all(&size.to_le_bytes())?;
-                data.write_all(&self.#field_name)?;
+                {
+                    let __flag: #flag_type = #flag;
+                    #encode_flag
+                    if let ::core::option::Option::Some(__value) = #reference {
+                        #encode
+                    }
+                }
             });
+            encoded_lens.push(quote! { (::std::mem::size_of::<#flag_type>() + #value_len) });
 
             deserialize_statements.push(quote! {
-                let size = {
-                    let size_bytes = std::mem::size_of::<#prefix_type>();
-                    if rdr.position() as usize + size_bytes > data.len() {
-                        return Err(std::io::Error::new(
-                            std::io::ErrorKind::InvalidData,
-                            concat!("Insufficient data for ", stringify!(#field_name), " size")
-                        ));
-                    }
-                    let mut size_buf = vec![0u8; size_bytes];
-                    rdr.read_exact(&mut size_buf)?;
-                    let size_array: [u8; std::mem::size_of::<#prefix_type>()] = size_buf
-                        .try_into()
-                        .map_err(|_| std::io::Error::new(
-                            std::io::ErrorKind::InvalidData,
-                            concat!("Failed to parse size for ", stringify!(#field_name))
-                        ))?;
-                    let size_value = #prefix_type::from_le_bytes(size_array) as usize;
-                    
-                    // Validate size to prevent excessive memory allocation
-                    const MAX_SIZE: usize = #MAX_PREFIXED_SIZE;
-                    if size_value > MAX_SIZE {
-                        return Err(std::io::Error::new(
-                            std::io::ErrorKind::InvalidData,
-                            concat!("Field ", stringify!(#field_name), " size exceeds maximum allowed")
-                        ));
-                    }
-                    
-                    size_value
+                let #field_name = {
+                    let __flag = #decode_flag;
+                    if #present { ::core::option::Option::Some(#decode) } else { ::core::option::Option::None }
                 };
-                let mut #field_name = vec![0u8; size];
-                rdr.read_exact(&mut #field_name)?;
             });
-        } else {
-            // Handle primitive types
-            // Check if the type is Vec or String using proper type analysis
-            let is_vec_or_string = match field_type {
-                Type::Path(type_path) => {
-                    if let Some(segment) = type_path.path.segments.last() {
-                        let ident = &segment.ident;
-                        ident == "Vec" || ident == "String"
-                    } else {
-                        false
-                    }
+        } else if let Some(attr) = present_if_attr {
+            let condition: Expr = attr.parse_args()?;
+
+            // Earlier fields are locals when decoding, so bind them the same way when encoding:
+            // copies of numbers and bools, references to anything else
+            let bindings = fields.iter().take(index).map(|(previous_name, previous)| {
+                let is_copy = is_number(&previous.ty)
+                    || type_ident(&previous.ty).is_some_and(|ident| ident == "bool");
+                let value = if is_copy {
+                    access.copy(previous_name)
+                } else {
+                    access.reference(previous_name)
+                };
+                quote! {
+                    #[allow(unused_variables)]
+                    let #previous_name = #value;
                 }
-                _ => false,
-            };
-            
-            if is_vec_or_string {
-                panic!("Vec and String fields must have a #[prefixed(type)] attribute");
-            }
+            });
 
             serialize_statements.push(quote! {
-                data.write_all(&self.#field_name.to_le_bytes())?;
+                {
+                    let __present: bool = {
+                        #(#bindings)*
+                        #condition
+                    };
+                    match #reference {
+                        ::core::option::Option::Some(__value) if __present => {
+                            #encode
+                        }
+                        ::core::option::Option::None if !__present => {}
+                        _ => return ::core::result::Result::Err(::std::io::Error::new(
+                            ::std::io::ErrorKind::InvalidData,
+                            ::core::concat!("Presence of ", ::core::stringify!(#field_name), " does not match its condition")
+                        )),
+                    }
+                }
             });
+            encoded_lens.push(value_len);
 
             deserialize_statements.push(quote! {
-                let #field_name = {
-                    let size = std::mem::size_of::<#field_type>();
-                    if rdr.position() as usize + size > data.len() {
-                        return Err(std::io::Error::new(
-                            std::io::ErrorKind::InvalidData,
-                            concat!("Insufficient data for ", stringify!(#field_name))
-                        ));
-                    }
-                    let mut buf = [0u8; std::mem::size_of::<#field_type>()];
-                    rdr.read_exact(&mut buf)?;
-                    #field_type::from_le_bytes(buf)
-                };
+                let #field_name = if #condition { ::core::option::Option::Some(#decode) } else { ::core::option::Option::None };
             });
         }
     }
 
-    let expanded = quote! {
-        impl Message for #name {
-            fn serialize(&self) -> std::io::Result<Vec<u8>> {
-                use std::io::Write;
-                let mut data = Vec::new();
-                #(#serialize_statements)*
-                Ok(data)
+    Ok((serialize_statements, encoded_lens, deserialize_statements))
+}
+
+/// Generates the serialize statements, the encoded length expression and the decode
+/// expression of a field without `#[optional]` or `#[present_if]`.
+///
+/// `value` is the place expression holding the field when serializing, e.g. `self.name`.
+fn field_statements(
+    field_name: &syn::Ident,
+    field_type: &Type,
+    attrs: &[syn::Attribute],
+    value: &proc_macro2::TokenStream,
+) -> syn::Result<(proc_macro2::TokenStream, proc_macro2::TokenStream, proc_macro2::TokenStream)> {
+    // Check for #[prefixed(type)], #[count(type)] and #[remainder] attributes
+    let prefixed_attr = attrs.iter().find(|attr| attr.path().is_ident("prefixed"));
+    let count_attr = attrs.iter().find(|attr| attr.path().is_ident("count"));
+    let remainder_attr = attrs.iter().find(|attr| attr.path().is_ident("remainder"));
+    let with_attr = attrs.iter().find(|attr| attr.path().is_ident("with"));
+    let format = field_format(attrs)?;
+
+    if let Some(attr) = with_attr {
+        // This is encoded by the `encode` and `decode` functions of a module
+        if let Some(other) = prefixed_attr.or(count_attr).or(remainder_attr).or(format.map(|(_, attr)| attr)) {
+            return Err(syn::Error::new_spanned(other, "#[with] cannot be combined with other encodings"));
+        }
+        let module: syn::Path = attr.parse_args()?;
+
+        return Ok((
+            quote! {
+                #module::encode(&#value, &mut *__data)?;
+            },
+            quote! {
+                {
+                    let mut __counter = __pokemmo::message::ByteCounter::default();
+                    let _ = #module::encode(&#value, &mut __counter);
+                    __counter.len()
+                }
+            },
+            quote! {
+                #module::decode(__rdr)?
+            },
+        ));
+    }
+
+    if let Some(attr) = remainder_attr {
+        // This is a Vec<u8> holding everything after the preceding fields
+        if let Some(other) = prefixed_attr.or(count_attr).or(format.map(|(_, attr)| attr)) {
+            return Err(syn::Error::new_spanned(other, "#[remainder] cannot be combined with other encodings"));
+        }
+        attr.meta.require_path_only()?;
+        if !is_byte_vec(field_type) && !is_byte_slice(field_type) {
+            return Err(syn::Error::new_spanned(field_type, "#[remainder] requires a Vec<u8> or &[u8] field"));
+        }
+
+        return Ok((
+            quote! {
+                __data.write_all(&#value)?;
+            },
+            quote! { #value.len() },
+            read_rest(is_byte_slice(field_type)),
+        ));
+    }
+
+    if let (Some((_, format_attr)), Some(_)) = (format, prefixed_attr.or(count_attr)) {
+        return Err(syn::Error::new_spanned(
+            format_attr,
+            "Number formats of a length prefix or count go inside its attribute, e.g. #[prefixed(u32, varint)]",
+        ));
+    }
+
+    if let Some(attr) = count_attr {
+        // This is a Vec<T> with an element count prefix, whose elements are any non-prefixed
+        // field type: numbers, bool, arrays or a type implementing Field
+        if let Some(prefixed_attr) = prefixed_attr {
+            return Err(syn::Error::new_spanned(
+                prefixed_attr,
+                "#[prefixed] and #[count] cannot be combined",
+            ));
+        }
+        let Some(element_type) = generic_inner(field_type, "Vec") else {
+            return Err(syn::Error::new_spanned(field_type, "#[count(type)] requires a Vec<T> field"));
+        };
+        let Prefix { prefix_type, format } = attr.parse_args()?;
+        let read_count = read_prefix(&prefix_type, format, field_name);
+        let write_count = encode_number(&prefix_type, format, &quote! { __count });
+        let count_len = number_len(&prefix_type, format, &quote! { (#value.len() as #prefix_type) });
+        check_value_type(element_type)?;
+        let encode_element = encode_value(element_type, NumberFormat::Le, &quote! { (*__element) });
+        let element_len = value_len(element_type, NumberFormat::Le, &quote! { (*__element) });
+        let decode_element = decode_value(element_type, NumberFormat::Le, field_name);
+
+        return Ok((
+            quote! {
+                let __count: #prefix_type = ::core::convert::TryInto::try_into(#value.len())
+                    .map_err(|__err| ::std::io::Error::new(::std::io::ErrorKind::InvalidData, __err))?;
+                #write_count
+                for __element in &#value {
+                    #encode_element
+                }
+            },
+            quote! {
+                (#count_len + #value
+                    .iter()
+                    .map(|__element| #element_len)
+                    .sum::<usize>())
+            },
+            quote! {
+                {
+                    let __count = #read_count;
+                    // Every element occupies input, so don't trust the count for preallocation
+                    let __remaining = __data.len().saturating_sub(__rdr.position() as usize);
+                    let mut __elements: #field_type = ::std::vec::Vec::with_capacity(__count.min(__remaining));
+                    for _ in 0..__count {
+                        __elements.push(#decode_element);
+                    }
+                    __elements
+                }
+            },
+        ));
+    }
+
+    if let Some(attr) = prefixed_attr {
+        // This is a Vec<u8> or String with a length prefix
+        let prefixed: Prefixed = attr.parse_args()?;
+        let prefix_type = &prefixed.prefix_type;
+        let read_size = read_prefix(prefix_type, prefixed.format, field_name);
+        let write_size = encode_number(prefix_type, prefixed.format, &quote! { __size });
+        let size_len = number_len(prefix_type, prefixed.format, &quote! { (#value.len() as #prefix_type) });
+
+        if type_ident(field_type).is_some_and(|ident| ident == "String") {
+            return string_statements(field_name, &prefixed, &read_size, value, false);
+        }
+        if is_str_ref(field_type) {
+            return string_statements(field_name, &prefixed, &read_size, value, true);
+        }
+
+        if let Some(span) = prefixed.encoding_span.or(prefixed.unit_span) {
+            return Err(syn::Error::new(span, "String encodings are only supported on String and &str fields"));
+        }
+        let borrowed = is_byte_slice(field_type);
+        if !is_byte_vec(field_type) && !borrowed {
+            return Err(syn::Error::new_spanned(
+                field_type,
+                "#[prefixed(type)] requires a Vec<u8>, &[u8], String or &str field; use #[count(type)] for a Vec of messages",
+            ));
+        }
+        let decode = if borrowed {
+            borrow_bytes(&read_size, field_name)
+        } else {
+            quote! {
+                {
+                    let __size = #read_size;
+                    let mut __buf = ::std::vec![0u8; __size];
+                    __rdr.read_exact(&mut __buf)?;
+                    __buf
+                }
             }
+        };
 
-            fn deserialize(data: &[u8]) -> std::io::Result<Self> {
-                use std::io::Read;
-                let mut rdr = std::io::Cursor::new(data);
-                #(#deserialize_statements)*
-                Ok(Self {
-                    #(#field_names),*
-                })
+        return Ok((
+            quote! {
+                let __size: #prefix_type = ::core::convert::TryInto::try_into(#value.len())
+                    .map_err(|__err| ::std::io::Error::new(::std::io::ErrorKind::InvalidData, __err))?;
+                #write_size
+                __data.write_all(&#value)?;
+            },
+            quote! { (#size_len + #value.len()) },
+            decode,
+        ));
+    }
+
+    check_value_type(field_type)?;
+
+    let format = match format {
+        Some((format, _)) => {
+            // Formats apply to numbers, including the elements of arrays
+            let mut number_type = field_type;
+            while let Type::Array(array) = number_type {
+                number_type = &array.elem;
             }
+            format.check(number_type)?;
+            format
         }
+        None => NumberFormat::Le,
     };
 
-    TokenStream::from(expanded)
+    Ok((
+        encode_value(field_type, format, value),
+        value_len(field_type, format, value),
+        decode_value(field_type, format, field_name),
+    ))
+}
+
+/// Returns `T` if the type is `wrapper<T>`, e.g. `Option<T>`.
+fn generic_inner<'a>(ty: &'a Type, wrapper: &str) -> Option<&'a Type> {
+    let Type::Path(type_path) = ty else {
+        return None;
+    };
+    let segment = type_path.path.segments.last()?;
+    if segment.ident != wrapper {
+        return None;
+    }
+    match &segment.arguments {
+        syn::PathArguments::AngleBracketed(arguments) => match arguments.args.first()? {
+            syn::GenericArgument::Type(inner) => Some(inner),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+/// Returns `true` if the type is `Vec<u8>`.
+fn is_byte_vec(ty: &Type) -> bool {
+    generic_inner(ty, "Vec").and_then(type_ident).is_some_and(|ident| ident == "u8")
+}
+
+/// Returns the referenced type if the type is a shared reference, e.g. `str` for `&'a str`.
+fn referenced(ty: &Type) -> Option<&Type> {
+    match ty {
+        Type::Reference(reference) if reference.mutability.is_none() => Some(&reference.elem),
+        _ => None,
+    }
+}
+
+/// Returns `true` if the type is `&[u8]`, with any lifetime.
+fn is_byte_slice(ty: &Type) -> bool {
+    match referenced(ty) {
+        Some(Type::Slice(slice)) => type_ident(&slice.elem).is_some_and(|ident| ident == "u8"),
+        _ => false,
+    }
+}
+
+/// Returns `true` if the type is `&str`, with any lifetime.
+fn is_str_ref(ty: &Type) -> bool {
+    referenced(ty).and_then(type_ident).is_some_and(|ident| ident == "str")
+}
+
+/// Returns an error spanned at the type if a field without attributes cannot have the type.
+///
+/// Numbers, `bool` and arrays of them are encoded directly and any other named type is assumed
+/// to implement `Field`; the compiler reports it if it does not.
+fn check_value_type(ty: &Type) -> syn::Result<()> {
+    match ty {
+        Type::Array(array) => check_value_type(&array.elem),
+        Type::Group(group) => check_value_type(&group.elem),
+        Type::Paren(paren) => check_value_type(&paren.elem),
+        Type::Reference(_) if is_str_ref(ty) => {
+            Err(syn::Error::new_spanned(ty, "&str fields require a #[prefixed(type)] attribute"))
+        }
+        Type::Reference(_) if is_byte_slice(ty) => Err(syn::Error::new_spanned(
+            ty,
+            "&[u8] fields require a #[prefixed(type)] or #[remainder] attribute",
+        )),
+        Type::Path(_) => {
+            let message = match type_ident(ty).map(|ident| ident.to_string()).as_deref() {
+                Some("String") => "String fields require a #[prefixed(type)] attribute",
+                Some("Vec") => "Vec fields require a #[prefixed(type)], #[count(type)] or #[remainder] attribute",
+                Some("Option") => "Option fields require an #[optional(type)] or #[present_if(condition)] attribute",
+                Some("usize" | "isize") => "Pointer-sized integers are not supported; use a fixed-size integer type",
+                Some("u128" | "i128" | "char") => "Unsupported field type",
+                _ => return Ok(()),
+            };
+            Err(syn::Error::new_spanned(ty, message))
+        }
+        _ => Err(syn::Error::new_spanned(ty, "Unsupported field type")),
+    }
+}
+
+/// Text encoding of a `String` field.
+#[derive(Clone, Copy)]
+enum Encoding {
+    Utf8,
+    Utf16,
+}
+
+/// What the length prefix of a `String` field counts.
+#[derive(Clone, Copy)]
+enum Unit {
+    /// Encoded bytes.
+    Bytes,
+    /// Characters: Unicode scalar values for UTF-8, code units for UTF-16 (Java `String.length()`).
+    Chars,
+}
+
+/// Wire format of a number.
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum NumberFormat {
+    /// Fixed-size little-endian bytes, the default.
+    Le,
+    /// Fixed-size big-endian bytes (`#[be]`).
+    Be,
+    /// Unsigned LEB128 of the value's bits (`#[varint]`).
+    Varint,
+    /// Zigzag-mapped signed value as unsigned LEB128 (`#[zigzag]`).
+    Zigzag,
+}
+
+impl NumberFormat {
+    /// Parses a format option name: `be`, `varint` or `zigzag`.
+    fn from_option(option: &syn::Ident) -> Option<Self> {
+        match option.to_string().as_str() {
+            "be" => Some(NumberFormat::Be),
+            "varint" => Some(NumberFormat::Varint),
+            "zigzag" => Some(NumberFormat::Zigzag),
+            _ => None,
+        }
+    }
+
+    /// Returns an error spanned at `ty` if the format does not apply to the number type `ty`.
+    fn check(self, ty: &Type) -> syn::Result<()> {
+        let (message, supported) = match self {
+            NumberFormat::Le => return Ok(()),
+            NumberFormat::Be => ("#[be] requires an integer or floating-point type", is_number(ty)),
+            NumberFormat::Varint => ("#[varint] requires an integer type", unsigned_of(ty).is_some()),
+            NumberFormat::Zigzag => (
+                "#[zigzag] requires a signed integer type",
+                type_ident(ty).is_some_and(|ident| ident.to_string().starts_with('i')) && unsigned_of(ty).is_some(),
+            ),
+        };
+        if supported {
+            Ok(())
+        } else {
+            Err(syn::Error::new_spanned(ty, message))
+        }
+    }
+}
+
+/// Returns the number format selected by a field's `#[be]`, `#[varint]` and `#[zigzag]` attributes.
+///
+/// `#[zigzag]` implies `#[varint]`, so the two can be combined.
+fn field_format(attrs: &[syn::Attribute]) -> syn::Result<Option<(NumberFormat, &syn::Attribute)>> {
+    let mut format: Option<(NumberFormat, &syn::Attribute)> = None;
+    for attr in attrs {
+        let Some(option) = attr.path().get_ident() else {
+            continue;
+        };
+        let Some(selected) = NumberFormat::from_option(option) else {
+            continue;
+        };
+        attr.meta.require_path_only()?;
+        format = match format {
+            None => Some((selected, attr)),
+            Some((previous, _)) if previous == selected => {
+                return Err(syn::Error::new_spanned(attr, "Duplicate number format attribute"))
+            }
+            Some((NumberFormat::Varint, _)) if selected == NumberFormat::Zigzag => Some((selected, attr)),
+            Some((NumberFormat::Zigzag, previous)) if selected == NumberFormat::Varint => {
+                Some((NumberFormat::Zigzag, previous))
+            }
+            Some(_) => return Err(syn::Error::new_spanned(attr, "Conflicting number format attribute")),
+        };
+    }
+    Ok(format)
+}
+
+/// Returns an error spanned at the type if it is not an integer type usable as a prefix or tag.
+fn check_prefix_type(prefix_type: &Type) -> syn::Result<()> {
+    if unsigned_of(prefix_type).is_none() {
+        return Err(syn::Error::new_spanned(
+            prefix_type,
+            "Expected an integer type: i8, i16, i32, i64, u8, u16, u32 or u64",
+        ));
+    }
+    Ok(())
+}
+
+/// Parsed `#[count(T, format)]` and `#[tag(T, format)]` attribute arguments.
+struct Prefix {
+    prefix_type: Type,
+    format: NumberFormat,
+}
+
+impl syn::parse::Parse for Prefix {
+    fn parse(input: syn::parse::ParseStream) -> syn::Result<Self> {
+        let prefix_type: Type = input.parse()?;
+        let mut format = NumberFormat::Le;
+        if !input.is_empty() {
+            input.parse::<syn::Token![,]>()?;
+            let option: syn::Ident = input.parse()?;
+            format = NumberFormat::from_option(&option).ok_or_else(|| {
+                syn::Error::new_spanned(&option, "Expected one of `be`, `varint` or `zigzag`")
+            })?;
+        }
+        if !input.is_empty() {
+            return Err(input.error("Unexpected argument"));
+        }
+        check_prefix_type(&prefix_type)?;
+        format.check(&prefix_type)?;
+        Ok(Prefix { prefix_type, format })
+    }
+}
+
+/// Parsed `#[prefixed(T, encoding, unit, format)]` attribute arguments.
+struct Prefixed {
+    prefix_type: Type,
+    format: NumberFormat,
+    encoding: Encoding,
+    encoding_span: Option<proc_macro2::Span>,
+    unit: Unit,
+    unit_span: Option<proc_macro2::Span>,
+}
+
+impl syn::parse::Parse for Prefixed {
+    fn parse(input: syn::parse::ParseStream) -> syn::Result<Self> {
+        let prefix_type: Type = input.parse()?;
+        let mut format = None;
+        let mut encoding = None;
+        let mut unit = None;
+
+        while !input.is_empty() {
+            input.parse::<syn::Token![,]>()?;
+            let option: syn::Ident = input.parse()?;
+            let conflict = match option.to_string().as_str() {
+                "utf8" => encoding.replace((Encoding::Utf8, option.span())).is_some(),
+                "utf16" => encoding.replace((Encoding::Utf16, option.span())).is_some(),
+                "bytes" => unit.replace((Unit::Bytes, option.span())).is_some(),
+                "chars" => unit.replace((Unit::Chars, option.span())).is_some(),
+                "be" | "varint" | "zigzag" => format.replace(NumberFormat::from_option(&option)).is_some(),
+                _ => {
+                    return Err(syn::Error::new_spanned(
+                        &option,
+                        "Expected one of `utf8`, `utf16`, `bytes`, `chars`, `be`, `varint` or `zigzag`",
+                    ))
+                }
+            };
+            if conflict {
+                return Err(syn::Error::new_spanned(&option, "Conflicting #[prefixed] option"));
+            }
+        }
+
+        let encoding_value = encoding.map_or(Encoding::Utf8, |(value, _)| value);
+        // UTF-16 strings are counted in code units by default, as Java does
+        let default_unit = match encoding_value {
+            Encoding::Utf8 => Unit::Bytes,
+            Encoding::Utf16 => Unit::Chars,
+        };
+        let format = format.flatten().unwrap_or(NumberFormat::Le);
+        check_prefix_type(&prefix_type)?;
+        format.check(&prefix_type)?;
+        Ok(Prefixed {
+            prefix_type,
+            format,
+            encoding: encoding_value,
+            encoding_span: encoding.map(|(_, span)| span),
+            unit: unit.map_or(default_unit, |(value, _)| value),
+            unit_span: unit.map(|(_, span)| span),
+        })
+    }
+}
+
+/// Returns `true` if the type is a primitive integer or floating-point type.
+fn is_number(ty: &Type) -> bool {
+    type_ident(ty).is_some_and(|ident| {
+        ["i8", "i16", "i32", "i64", "u8", "u16", "u32", "u64", "f32", "f64"]
+            .iter()
+            .any(|number| ident == number)
+    })
+}
+
+/// Returns the unsigned integer type of the same width as the integer type `ty`.
+fn unsigned_of(ty: &Type) -> Option<syn::Ident> {
+    let unsigned = match type_ident(ty)?.to_string().as_str() {
+        "i8" | "u8" => "u8",
+        "i16" | "u16" => "u16",
+        "i32" | "u32" => "u32",
+        "i64" | "u64" => "u64",
+        _ => return None,
+    };
+    Some(syn::Ident::new(unsigned, proc_macro2::Span::call_site()))
+}
+
+/// Generates an expression of the unsigned bits a `#[varint]` or `#[zigzag]` number is
+/// LEB128 encoded from.
+fn varint_bits(ty: &Type, format: NumberFormat, value: &proc_macro2::TokenStream) -> proc_macro2::TokenStream {
+    let unsigned = unsigned_of(ty);
+    match format {
+        NumberFormat::Zigzag => quote! {
+            {
+                let __value: #ty = #value;
+                #unsigned::from_ne_bytes(((__value << 1) ^ (__value >> (#ty::BITS - 1))).to_ne_bytes())
+            }
+        },
+        // Reinterpret the value as unsigned to shift in zeros
+        _ => quote! { #unsigned::from_ne_bytes(#value.to_ne_bytes()) },
+    }
+}
+
+/// Generates statements writing `value`, an expression of number type `ty`, in `format`.
+fn encode_number(ty: &Type, format: NumberFormat, value: &proc_macro2::TokenStream) -> proc_macro2::TokenStream {
+    let unsigned = unsigned_of(ty);
+    let bits = match format {
+        NumberFormat::Le => {
+            return quote! {
+                __data.write_all(&#value.to_le_bytes())?;
+            }
+        }
+        NumberFormat::Be => {
+            return quote! {
+                __data.write_all(&#value.to_be_bytes())?;
+            }
+        }
+        NumberFormat::Varint | NumberFormat::Zigzag => varint_bits(ty, format, value),
+    };
+    quote! {
+        {
+            let mut __rest: #unsigned = #bits;
+            loop {
+                let __byte = (__rest & 0x7F) as u8;
+                __rest >>= 7;
+                if __rest == 0 {
+                    __data.write_all(&[__byte])?;
+                    break;
+                }
+                __data.write_all(&[__byte | 0x80])?;
+            }
+        }
+    }
+}
+
+/// Generates an expression of the number of bytes [`encode_number`] writes for `value`.
+///
+/// Fixed-size formats don't evaluate `value`.
+fn number_len(ty: &Type, format: NumberFormat, value: &proc_macro2::TokenStream) -> proc_macro2::TokenStream {
+    match format {
+        NumberFormat::Le | NumberFormat::Be => quote! { ::std::mem::size_of::<#ty>() },
+        NumberFormat::Varint | NumberFormat::Zigzag => {
+            let unsigned = unsigned_of(ty);
+            let bits = varint_bits(ty, format, value);
+            quote! {
+                {
+                    let __bits: #unsigned = #bits;
+                    // 7 bits per byte; zero still takes one byte
+                    ((#unsigned::BITS - __bits.leading_zeros()).max(1) as usize).div_ceil(7)
+                }
+            }
+        }
+    }
+}
+
+/// Generates an expression reading a number of type `ty` in `format`; see [`encode_number`].
+fn decode_number(ty: &Type, format: NumberFormat, field_name: &syn::Ident) -> proc_macro2::TokenStream {
+    let unsigned = unsigned_of(ty);
+    let from_bytes = match format {
+        NumberFormat::Le => quote! { from_le_bytes },
+        NumberFormat::Be => quote! { from_be_bytes },
+        NumberFormat::Varint | NumberFormat::Zigzag => {
+            let value = if format == NumberFormat::Zigzag {
+                quote! { #ty::from_ne_bytes(((__bits >> 1) ^ (__bits & 1).wrapping_neg()).to_ne_bytes()) }
+            } else {
+                quote! { #ty::from_ne_bytes(__bits.to_ne_bytes()) }
+            };
+            return quote! {
+                {
+                    let mut __bits: #unsigned = 0;
+                    let mut __shift = 0u32;
+                    loop {
+                        let mut __byte = [0u8; 1];
+                        if __rdr.position() as usize >= __data.len() {
+                            return ::core::result::Result::Err(::std::io::Error::new(
+                                ::std::io::ErrorKind::InvalidData,
+                                ::core::concat!("Insufficient data for ", ::core::stringify!(#field_name))
+                            ));
+                        }
+                        __rdr.read_exact(&mut __byte)?;
+                        // A zero final byte after the first only adds padding, so each value
+                        // has exactly one accepted encoding
+                        if __byte[0] == 0 && __shift > 0 {
+                            return ::core::result::Result::Err(::std::io::Error::new(
+                                ::std::io::ErrorKind::InvalidData,
+                                ::core::concat!("Overlong varint for ", ::core::stringify!(#field_name))
+                            ));
+                        }
+                        let __chunk = #unsigned::from(__byte[0] & 0x7F);
+                        if __shift >= #unsigned::BITS || (__chunk << __shift) >> __shift != __chunk {
+                            return ::core::result::Result::Err(::std::io::Error::new(
+                                ::std::io::ErrorKind::InvalidData,
+                                ::core::concat!("Varint overflow for ", ::core::stringify!(#field_name))
+                            ));
+                        }
+                        __bits |= __chunk << __shift;
+                        if __byte[0] & 0x80 == 0 {
+                            break;
+                        }
+                        __shift += 7;
+                    }
+                    #value
+                }
+            };
+        }
+    };
+    quote! {
+        {
+            let __size = ::std::mem::size_of::<#ty>();
+            if __rdr.position() as usize + __size > __data.len() {
+                return ::core::result::Result::Err(::std::io::Error::new(
+                    ::std::io::ErrorKind::InvalidData,
+                    ::core::concat!("Insufficient data for ", ::core::stringify!(#field_name))
+                ));
+            }
+            let mut __buf = [0u8; ::std::mem::size_of::<#ty>()];
+            __rdr.read_exact(&mut __buf)?;
+            #ty::#from_bytes(__buf)
+        }
+    }
+}
+
+/// Generates statements writing `value`, a place expression of type `ty`, without a prefix.
+///
+/// Numbers are written in `format`, `bool` is a single 0/1 byte, arrays are their elements in
+/// order and any other type is a nested message.
+fn encode_value(ty: &Type, format: NumberFormat, value: &proc_macro2::TokenStream) -> proc_macro2::TokenStream {
+    if is_number(ty) {
+        return encode_number(ty, format, value);
+    }
+
+    if type_ident(ty).is_some_and(|ident| ident == "bool") {
+        return quote! {
+            __data.write_all(&[#value as u8])?;
+        };
+    }
+
+    if let Type::Array(array) = ty {
+        // Byte arrays are copied as is unless a variable-length format applies to each byte
+        if type_ident(&array.elem).is_some_and(|ident| ident == "u8") && is_fixed_size(&array.elem, format) {
+            return quote! {
+                __data.write_all(&#value)?;
+            };
+        }
+        let encode_element = encode_value(&array.elem, format, &quote! { (*__element) });
+        return quote! {
+            for __element in &#value {
+                #encode_element
+            }
+        };
+    }
+
+    // Any other type encodes itself, e.g. a nested message
+    quote! {
+        <#ty as __pokemmo::message::Field>::encode(&#value, &mut *__data)?;
+    }
+}
+
+/// Generates an expression of the number of bytes [`encode_value`] writes for `value`.
+fn value_len(ty: &Type, format: NumberFormat, value: &proc_macro2::TokenStream) -> proc_macro2::TokenStream {
+    if is_fixed_size(ty, format) {
+        return quote! { ::std::mem::size_of::<#ty>() };
+    }
+
+    if is_number(ty) {
+        return number_len(ty, format, value);
+    }
+
+    if let Type::Array(array) = ty {
+        let element_len = value_len(&array.elem, format, &quote! { (*__element) });
+        return quote! {
+            #value.iter().map(|__element| #element_len).sum::<usize>()
+        };
+    }
+
+    quote! {
+        <#ty as __pokemmo::message::Field>::encoded_size(&#value)
+    }
+}
+
+/// Returns `true` if [`encode_value`] writes `size_of::<ty>()` bytes for any value: numbers in
+/// a fixed-size format, `bool`, and arrays of those.
+fn is_fixed_size(ty: &Type, format: NumberFormat) -> bool {
+    match ty {
+        Type::Array(array) => is_fixed_size(&array.elem, format),
+        _ if is_number(ty) => matches!(format, NumberFormat::Le | NumberFormat::Be),
+        _ => type_ident(ty).is_some_and(|ident| ident == "bool"),
+    }
+}
+
+/// Generates an expression reading a value of type `ty` without a prefix; see [`encode_value`].
+fn decode_value(ty: &Type, format: NumberFormat, field_name: &syn::Ident) -> proc_macro2::TokenStream {
+    if is_number(ty) {
+        return decode_number(ty, format, field_name);
+    }
+
+    if type_ident(ty).is_some_and(|ident| ident == "bool") {
+        return quote! {
+            {
+                let mut __buf = [0u8; 1];
+                __rdr.read_exact(&mut __buf)?;
+                match __buf[0] {
+                    0 => false,
+                    1 => true,
+                    _ => return ::core::result::Result::Err(::std::io::Error::new(
+                        ::std::io::ErrorKind::InvalidData,
+                        ::core::concat!("Invalid bool value for ", ::core::stringify!(#field_name))
+                    )),
+                }
+            }
+        };
+    }
+
+    if let Type::Array(array) = ty {
+        let len = &array.len;
+        if type_ident(&array.elem).is_some_and(|ident| ident == "u8") && is_fixed_size(&array.elem, format) {
+            return quote! {
+                {
+                    let mut __buf = [0u8; #len];
+                    __rdr.read_exact(&mut __buf)?;
+                    __buf
+                }
+            };
+        }
+        let decode_element = decode_value(&array.elem, format, field_name);
+        return quote! {
+            {
+                let mut __elements = ::std::vec::Vec::with_capacity(#len);
+                for _ in 0..#len {
+                    __elements.push(#decode_element);
+                }
+                match <#ty as ::core::convert::TryFrom<_>>::try_from(__elements) {
+                    ::core::result::Result::Ok(__array) => __array,
+                    ::core::result::Result::Err(_) => ::core::unreachable!("array length is fixed"),
+                }
+            }
+        };
+    }
+
+    // Any other type decodes itself, e.g. a nested message
+    quote! {
+        <#ty as __pokemmo::message::Field>::decode(__rdr)?
+    }
+}
+
+/// Returns the last path segment identifier of a type, e.g. `Vec` for `std::vec::Vec<u8>`.
+fn type_ident(ty: &Type) -> Option<&syn::Ident> {
+    match ty {
+        Type::Path(type_path) => type_path.path.segments.last().map(|segment| &segment.ident),
+        _ => None,
+    }
+}
+
+/// Generates an expression taking all bytes left in the input: a `&[u8]` pointing into it if
+/// `borrowed`, otherwise a copied `Vec<u8>`.
+fn read_rest(borrowed: bool) -> proc_macro2::TokenStream {
+    if borrowed {
+        quote! {
+            {
+                let __start = (__rdr.position() as usize).min(__data.len());
+                __rdr.set_position(__rdr.position().max(__data.len() as u64));
+                &__data[__start..]
+            }
+        }
+    } else {
+        quote! {
+            {
+                let mut __rest = ::std::vec::Vec::new();
+                __rdr.read_to_end(&mut __rest)?;
+                __rest
+            }
+        }
+    }
+}
+
+/// Generates an expression borrowing the next `len` bytes of the input as a `&[u8]`,
+/// advancing past them.
+fn borrow_bytes(len: &proc_macro2::TokenStream, field_name: &syn::Ident) -> proc_macro2::TokenStream {
+    quote! {
+        {
+            let __len: usize = #len;
+            let __start = __rdr.position() as usize;
+            let __end = __start
+                .checked_add(__len)
+                .filter(|&__end| __end <= __data.len())
+                .ok_or_else(|| ::std::io::Error::new(
+                    ::std::io::ErrorKind::InvalidData,
+                    ::core::concat!("Insufficient data for ", ::core::stringify!(#field_name))
+                ))?;
+            __rdr.set_position(__end as u64);
+            &__data[__start..__end]
+        }
+    }
+}
+
+/// Generates an expression reading a length prefix of type `prefix_type` in `format` as `usize`.
+fn read_prefix(prefix_type: &Type, format: NumberFormat, field_name: &syn::Ident) -> proc_macro2::TokenStream {
+    let size_name = quote::format_ident!("{}_size", field_name);
+    let decode = decode_number(prefix_type, format, &size_name);
+    quote! {
+        {
+            let __size_value = #decode as usize;
+
+            // Validate size to prevent excessive memory allocation
+            const MAX_SIZE: usize = #MAX_PREFIXED_SIZE;
+            if __size_value > MAX_SIZE {
+                return ::core::result::Result::Err(::std::io::Error::new(
+                    ::std::io::ErrorKind::InvalidData,
+                    ::core::concat!("Field ", ::core::stringify!(#field_name), " size exceeds maximum allowed")
+                ));
+            }
+
+            __size_value
+        }
+    }
+}
+
+/// Generates the serialize statements, the encoded length expression and the decode
+/// expression of a `#[prefixed]` `String` field, or of a `&str` field if `borrowed`.
+///
+/// A `&str` points into the input, so it must be UTF-8.
+fn string_statements(
+    field_name: &syn::Ident,
+    prefixed: &Prefixed,
+    read_size: &proc_macro2::TokenStream,
+    value: &proc_macro2::TokenStream,
+    borrowed: bool,
+) -> syn::Result<(proc_macro2::TokenStream, proc_macro2::TokenStream, proc_macro2::TokenStream)> {
+    let prefix_type = &prefixed.prefix_type;
+    let write_size = encode_number(prefix_type, prefixed.format, &quote! { __size });
+    let invalid = quote! {
+        |_| ::std::io::Error::new(
+            ::std::io::ErrorKind::InvalidData,
+            ::core::concat!("Invalid string data for ", ::core::stringify!(#field_name))
+        )
+    };
+
+    match prefixed.encoding {
+        Encoding::Utf8 => {
+            let count = match prefixed.unit {
+                Unit::Bytes => quote! { #value.len() },
+                Unit::Chars => quote! { #value.chars().count() },
+            };
+            let size_len = number_len(prefix_type, prefixed.format, &quote! { ((#count) as #prefix_type) });
+            let decode = match prefixed.unit {
+                Unit::Bytes if borrowed => {
+                    let bytes = borrow_bytes(read_size, field_name);
+                    quote! { ::std::str::from_utf8(#bytes).map_err(#invalid)? }
+                }
+                Unit::Chars if borrowed => {
+                    let bytes = borrow_bytes(&quote! { __len }, field_name);
+                    quote! {
+                        {
+                            let __size = #read_size;
+                            // Measure one UTF-8 sequence per character, sized by its leading byte
+                            let __start = __rdr.position() as usize;
+                            let mut __len = 0usize;
+                            for _ in 0..__size {
+                                let ::core::option::Option::Some(&__lead) = __data.get(__start.saturating_add(__len)) else {
+                                    return ::core::result::Result::Err(::std::io::Error::new(
+                                        ::std::io::ErrorKind::InvalidData,
+                                        ::core::concat!("Insufficient data for ", ::core::stringify!(#field_name))
+                                    ));
+                                };
+                                __len += match __lead {
+                                    0x00..=0x7F => 1,
+                                    0xC0..=0xDF => 2,
+                                    0xE0..=0xEF => 3,
+                                    0xF0..=0xF7 => 4,
+                                    _ => return ::core::result::Result::Err((#invalid)(())),
+                                };
+                            }
+                            ::std::str::from_utf8(#bytes).map_err(#invalid)?
+                        }
+                    }
+                }
+                Unit::Bytes => quote! {
+                    {
+                        let __size = #read_size;
+                        let mut __buf = ::std::vec![0u8; __size];
+                        __rdr.read_exact(&mut __buf)?;
+                        ::std::string::String::from_utf8(__buf).map_err(#invalid)?
+                    }
+                },
+                Unit::Chars => quote! {
+                    {
+                        let __size = #read_size;
+                        // Read one UTF-8 sequence per character, sized by its leading byte
+                        let mut __buf = ::std::vec::Vec::with_capacity(__size);
+                        for _ in 0..__size {
+                            let mut __lead = [0u8; 1];
+                            __rdr.read_exact(&mut __lead)?;
+                            let __width = match __lead[0] {
+                                0x00..=0x7F => 1,
+                                0xC0..=0xDF => 2,
+                                0xE0..=0xEF => 3,
+                                0xF0..=0xF7 => 4,
+                                _ => return ::core::result::Result::Err((#invalid)(())),
+                            };
+                            __buf.push(__lead[0]);
+                            let __start = __buf.len();
+                            __buf.resize(__start + __width - 1, 0);
+                            __rdr.read_exact(&mut __buf[__start..])?;
+                        }
+                        ::std::string::String::from_utf8(__buf).map_err(#invalid)?
+                    }
+                },
+            };
+            Ok((
+                quote! {
+                    let __size: #prefix_type = ::core::convert::TryInto::try_into(#count)
+                        .map_err(|__err| ::std::io::Error::new(::std::io::ErrorKind::InvalidData, __err))?;
+                    #write_size
+                    __data.write_all(#value.as_bytes())?;
+                },
+                quote! { (#size_len + #value.len()) },
+                decode,
+            ))
+        }
+        Encoding::Utf16 if borrowed => Err(syn::Error::new(
+            prefixed.encoding_span.unwrap_or_else(proc_macro2::Span::call_site),
+            "UTF-16 strings cannot be borrowed; use a String field",
+        )),
+        Encoding::Utf16 => {
+            // Counted from the code unit iterator so that serializing doesn't allocate
+            let count = match prefixed.unit {
+                Unit::Bytes => quote! { #value.encode_utf16().count() * 2 },
+                Unit::Chars => quote! { #value.encode_utf16().count() },
+            };
+            let size_len = number_len(prefix_type, prefixed.format, &quote! { ((#count) as #prefix_type) });
+            let byte_len = match prefixed.unit {
+                Unit::Bytes => quote! {
+                    if __size % 2 != 0 {
+                        return ::core::result::Result::Err((#invalid)(()));
+                    }
+                    __size
+                },
+                Unit::Chars => quote! { __size * 2 },
+            };
+            Ok((
+                quote! {
+                    let __size: #prefix_type = ::core::convert::TryInto::try_into((#count))
+                        .map_err(|__err| ::std::io::Error::new(::std::io::ErrorKind::InvalidData, __err))?;
+                    #write_size
+                    for __unit in #value.encode_utf16() {
+                        __data.write_all(&__unit.to_le_bytes())?;
+                    }
+                },
+                quote! { (#size_len + #value.encode_utf16().count() * 2) },
+                quote! {
+                    {
+                        let __size = #read_size;
+                        let mut __buf = ::std::vec![0u8; { #byte_len }];
+                        __rdr.read_exact(&mut __buf)?;
+                        let __units: ::std::vec::Vec<u16> = __buf
+                            .chunks_exact(2)
+                            .map(|__unit| u16::from_le_bytes([__unit[0], __unit[1]]))
+                            .collect();
+                        ::std::string::String::from_utf16(&__units).map_err(#invalid)?
+                    }
+                },
+            ))
+        }
+    }
 }
 
 /// A procedural macro that implements the `Codec` trait for enums with opcode-based message routing.
@@ -198,13 +1701,28 @@ pub fn derive_message(input: TokenStream) -> TokenStream {
 ///   - The field type must implement the `Message` trait.
 /// - **Unknown variant** (optional): Must have named fields:
 ///   - `opcode`: Type `u8` or `i8` (encoded/decoded as a single little-endian byte).
-///   - `data`: Type `Vec<u8>` carrying the raw payload.
+///   - `data`: Type `Vec<u8>` carrying a copy of the raw payload, or `&'a [u8]` pointing into
+///     the decoded input.
+/// - **Generics**: The enum's generics and where clause are carried through.
+///   - Lifetime parameters allow borrowed messages; the generated impl is `Codec<'de>` for
+///     inputs of lifetime `'de` outliving them.
+///   - With type parameters, every payload type is bounded by `Message<'de>`.
+///   - A variant whose payload is a bare type parameter, e.g. `Single(T)`, gets no `Into`
+///     and `TryFrom` impls, which the orphan rules forbid.
+///
+/// ## Crate Path
+///
+/// The generated impls refer to `Codec` and `Message` through the `pokemmo` crate, so the
+/// codec can be declared in any crate depending on it. If pokemmo is reachable under another
+/// path, e.g. renamed in `Cargo.toml` or re-exported, pass it as `#[codec(crate = path)]`.
 ///
 /// ## Behavior
 ///
-/// - **`encode()`**: Prepends the opcode to the serialized message payload.
+/// - **`encode_into()`**: Writes the opcode, then serializes the message payload into the
+///   same writer; `encode()` collects this into an exact-size vector.
 ///   - For known variants, the opcode is cast to `u8`.
 ///   - For `Unknown`, the opcode is encoded as a single LE byte (supports `u8` or `i8`).
+/// - **`encoded_len()`**: One opcode byte plus the payload's `Message::encoded_len()`.
 /// - **`decode()`**: Reads the first byte as the opcode and dispatches to the appropriate variant.
 ///   - If the opcode matches a known variant, deserializes the payload via `Message::deserialize()`.
 ///   - Otherwise, falls back to `Unknown` (mapping the byte to the declared opcode type).
@@ -235,7 +1753,10 @@ pub fn derive_message(input: TokenStream) -> TokenStream {
 ///
 /// These enable ergonomic type conversion via `.into()` and `.try_into()`.
 #[proc_macro_attribute]
-pub fn codec(_attr: TokenStream, item: TokenStream) -> TokenStream {
+pub fn codec(attr: TokenStream, item: TokenStream) -> TokenStream {
+    let mut krate = default_crate_path();
+    let options = syn::meta::parser(|meta| parse_crate_option(meta, &mut krate));
+    parse_macro_input!(attr with options);
     let input = parse_macro_input!(item as DeriveInput);
     
     let enum_name = &input.ident;
@@ -257,6 +1778,8 @@ pub fn codec(_attr: TokenStream, item: TokenStream) -> TokenStream {
     // Track Unknown variant presence and whether its opcode is i8
     let mut has_unknown = false;
     let mut unknown_is_i8 = false;
+    // Whether the Unknown data is a `&[u8]` view of the input rather than a copy
+    let mut unknown_is_borrowed = false;
     
     for variant in &data_enum.variants {
         let variant_name = &variant.ident;
@@ -269,6 +1792,9 @@ pub fn codec(_attr: TokenStream, item: TokenStream) -> TokenStream {
                 Fields::Named(named) => {
                     for f in &named.named {
                         if let Some(ident) = &f.ident {
+                            if ident == "data" {
+                                unknown_is_borrowed = is_byte_slice(&f.ty);
+                            }
                             if ident == "opcode" {
                                 if let Type::Path(tp) = &f.ty {
                                     if let Some(seg) = tp.path.segments.last() {
@@ -347,6 +1873,19 @@ pub fn codec(_attr: TokenStream, item: TokenStream) -> TokenStream {
         variants_with_opcodes.push((variant_name.clone(), opcode, inner_type.clone()));
     }
     
+    let generics = &input.generics;
+    let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
+    // Payloads of a generic codec must be messages for any input the codec decodes
+    let payload_bounds = if generics.type_params().next().is_some() {
+        variants_with_opcodes
+            .iter()
+            .map(|(_, _, inner_type)| syn::parse_quote! { #inner_type: __pokemmo::message::Message<'__de> })
+            .collect()
+    } else {
+        Vec::new()
+    };
+    let codec_impl = decode_impl(&input, quote! { __pokemmo::codec::Codec }, payload_bounds);
+    
     // Generate the enum definition without discriminants
     let enum_variants = data_enum.variants.iter().map(|v| {
         let variant_name = &v.ident;
@@ -354,18 +1893,22 @@ pub fn codec(_attr: TokenStream, item: TokenStream) -> TokenStream {
         quote! { #variant_name #fields }
     });
     
-    // Generate encode match arms
+    // Generate encode and encoded length match arms
     let mut all_encode_arms = variants_with_opcodes.iter().map(|(name, opcode, _)| {
         quote! {
             #enum_name::#name(msg) => {
-                let mut msg_data = vec![#opcode as u8];
-                msg_data.extend_from_slice(&msg.serialize()?);
-                msg_data
+                writer.write_all(&[#opcode as u8])?;
+                __pokemmo::message::Message::serialize_into(msg, writer)
             }
         }
     }).collect::<Vec<_>>();
+    let mut encoded_len_arms = variants_with_opcodes.iter().map(|(name, _, _)| {
+        quote! {
+            #enum_name::#name(msg) => 1 + __pokemmo::message::Message::encoded_len(msg)
+        }
+    }).collect::<Vec<_>>();
     
-    // Add unknown variant arm if present
+    // Add unknown variant arms if present
     if has_unknown {
         let opcode_push = if unknown_is_i8 {
             quote! { i8::to_le_bytes(*opcode)[0] }
@@ -374,19 +1917,20 @@ pub fn codec(_attr: TokenStream, item: TokenStream) -> TokenStream {
         };
         all_encode_arms.push(quote! {
             #enum_name::Unknown { opcode, data } => {
-                let mut msg_data = Vec::with_capacity(1 + data.len());
-                msg_data.push(#opcode_push);
-                msg_data.extend_from_slice(data);
-                msg_data
+                writer.write_all(&[#opcode_push])?;
+                writer.write_all(data)
             }
         });
+        encoded_len_arms.push(quote! {
+            #enum_name::Unknown { data, .. } => 1 + data.len()
+        });
     }
     
     // Generate decode match arms
     let decode_arms = variants_with_opcodes.iter().map(|(name, opcode, inner_type)| {
         quote! {
-            #opcode => Ok(#enum_name::#name(
-                #inner_type::deserialize(&data[1..])?
+            #opcode => ::core::result::Result::Ok(#enum_name::#name(
+                <#inner_type as __pokemmo::message::Message>::deserialize(&data[1..])?
             ))
         }
     });
@@ -397,26 +1941,38 @@ pub fn codec(_attr: TokenStream, item: TokenStream) -> TokenStream {
         } else {
             quote! { opcode }
         };
+        let data_expr = if unknown_is_borrowed {
+            quote! { &data[1..] }
+        } else {
+            quote! { data[1..].to_vec() }
+        };
         quote! {
-            opcode => Ok(#enum_name::Unknown {
+            opcode => ::core::result::Result::Ok(#enum_name::Unknown {
                 opcode: #opcode_expr,
-                data: data[1..].to_vec(),
+                data: #data_expr,
             })
         }
     } else {
         quote! {
-            _ => Err(std::io::Error::new(
-                std::io::ErrorKind::InvalidData,
+            _ => ::core::result::Result::Err(::std::io::Error::new(
+                ::std::io::ErrorKind::InvalidData,
                 "Unknown message opcode"
             ))
         }
     };
     
     // Generate Into implementations
-    let into_impls = variants_with_opcodes.iter().map(|(name, _, inner_type)| {
+    // The orphan rules reject conversions between the codec and a payload that is a bare type
+    // parameter, e.g. `impl<T> Into<Envelope<T>> for T`, so those variants get none
+    let converted = variants_with_opcodes.iter().filter(|(_, _, inner_type)| {
+        !generics
+            .type_params()
+            .any(|param| matches!(inner_type, Type::Path(path) if path.qself.is_none() && path.path.is_ident(&param.ident)))
+    });
+    let into_impls = converted.clone().map(|(name, _, inner_type)| {
         quote! {
-            impl Into<#enum_name> for #inner_type {
-                fn into(self) -> #enum_name {
+            impl #impl_generics ::core::convert::Into<#enum_name #ty_generics> for #inner_type #where_clause {
+                fn into(self) -> #enum_name #ty_generics {
                     #enum_name::#name(self)
                 }
             }
@@ -424,41 +1980,39 @@ pub fn codec(_attr: TokenStream, item: TokenStream) -> TokenStream {
     });
     
     // Generate TryFrom implementations
-    let try_from_impls = variants_with_opcodes.iter().map(|(name, _, inner_type)| {
+    let try_from_impls = converted.map(|(name, _, inner_type)| {
         quote! {
-            impl TryFrom<#enum_name> for #inner_type {
+            impl #impl_generics ::core::convert::TryFrom<#enum_name #ty_generics> for #inner_type #where_clause {
                 type Error = ();
                 
-                fn try_from(value: #enum_name) -> Result<Self, Self::Error> {
+                fn try_from(value: #enum_name #ty_generics) -> ::core::result::Result<Self, Self::Error> {
                     match value {
-                        #enum_name::#name(msg) => Ok(msg),
-                        _ => Err(()),
+                        #enum_name::#name(msg) => ::core::result::Result::Ok(msg),
+                        _ => ::core::result::Result::Err(()),
                     }
                 }
             }
         }
     });
     
-    let expanded = quote! {
-        #vis enum #enum_name {
-            #(#enum_variants),*
-        }
-        
-        impl super::Codec for #enum_name {
-            fn encode(&self) -> std::io::Result<Vec<u8>> {
-                use crate::message::Message;
-                
-                Ok(match self {
+    let impls = quote! {
+        #codec_impl {
+            fn encode_into(&self, writer: &mut impl ::std::io::Write) -> ::std::io::Result<()> {
+                match self {
                     #(#all_encode_arms),*
-                })
+                }
             }
             
-            fn decode(data: &[u8]) -> std::io::Result<Self> {
-                use crate::message::Message;
-                
+            fn encoded_len(&self) -> usize {
+                match self {
+                    #(#encoded_len_arms),*
+                }
+            }
+            
+            fn decode(data: &'__de [u8]) -> ::std::io::Result<Self> {
                 if data.is_empty() {
-                    return Err(std::io::Error::new(
-                        std::io::ErrorKind::InvalidData,
+                    return ::core::result::Result::Err(::std::io::Error::new(
+                        ::std::io::ErrorKind::InvalidData,
                         "No opcode found in message"
                     ));
                 }
@@ -474,6 +2028,15 @@ pub fn codec(_attr: TokenStream, item: TokenStream) -> TokenStream {
         
         #(#try_from_impls)*
     };
+    let impls = with_crate(&krate, impls);
+    
+    let expanded = quote! {
+        #vis enum #enum_name #generics #where_clause {
+            #(#enum_variants),*
+        }
+        
+        #impls
+    };
     
     TokenStream::from(expanded)
 }
@@ -8,6 +8,12 @@ pub use pokemmo_macros::codec;
 /// Implementations of this trait are typically generated by the `#[codec]` procedural macro,
 /// which creates enum variants that map to specific opcodes and message types.
 ///
+/// Like [`Message`](crate::message::Message), the trait is parameterized by the lifetime
+/// `'de` of the input. A codec whose variants hold borrowed messages, and whose `Unknown`
+/// variant holds `data: &'a [u8]`, decodes into views of the frame buffer without copying;
+/// [`ContextedStream::read_frame`](crate::context::ContextedStream::read_frame) provides
+/// that buffer. Codecs that own their data implement [`CodecOwned`].
+///
 /// ## Examples
 ///
 /// ```ignore
@@ -17,16 +23,33 @@ pub use pokemmo_macros::codec;
 /// let encoded = msg.encode()?;
 /// let decoded = Login::decode(&encoded)?;
 /// ```
-pub trait Codec {
+pub trait Codec<'de> {
     /// Encodes this codec variant into a byte vector.
     ///
     /// The encoded format is: `[opcode, payload...]` where the opcode is the
-    /// first byte and the payload is the serialized message data.
+    /// first byte and the payload is the serialized message data. The vector is
+    /// allocated once, sized by [`Codec::encoded_len`].
     ///
     /// ## Errors
     ///
     /// Returns an error if serialization of the message fails.
-    fn encode(&self) -> std::io::Result<Vec<u8>>;
+    fn encode(&self) -> std::io::Result<Vec<u8>> {
+        let mut data = Vec::with_capacity(self.encoded_len());
+        self.encode_into(&mut data)?;
+        Ok(data)
+    }
+
+    /// Encodes this codec variant into a writer, without intermediate buffers.
+    ///
+    /// Writes exactly the bytes [`Codec::encode`] returns.
+    ///
+    /// ## Errors
+    ///
+    /// Returns an error if serialization of the message or writing fails.
+    fn encode_into(&self, writer: &mut impl std::io::Write) -> std::io::Result<()>;
+
+    /// Returns the number of bytes [`Codec::encode`] produces for this variant.
+    fn encoded_len(&self) -> usize;
 
     /// Decodes a byte slice into a codec variant.
     ///
@@ -39,7 +62,15 @@ pub trait Codec {
     /// - The data is empty (no opcode byte).
     /// - The opcode is unknown and no `Unknown` variant is defined.
     /// - Deserialization of the message payload fails.
-    fn decode(data: &[u8]) -> std::io::Result<Self>
+    fn decode(data: &'de [u8]) -> std::io::Result<Self>
     where
         Self: Sized;
 }
+
+/// A [`Codec`] that borrows nothing from its input, as required by
+/// [`ContextedStream`](crate::context::ContextedStream) to decode messages it returns.
+///
+/// Implemented for every type implementing `Codec<'de>` for all lifetimes `'de`.
+pub trait CodecOwned: for<'de> Codec<'de> {}
+
+impl<T> CodecOwned for T where T: for<'de> Codec<'de> {}
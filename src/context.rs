@@ -8,8 +8,9 @@
 /// - Handshake phase (unencrypted): `Length (i16 LE) || Packet`
 /// - Secure phase (encrypted): `Length (i16 LE) || Encrypted Data || Checksum`
 ///
-/// Note: This crate currently demonstrates the handshake message flow; encryption and
-/// checksums are documented in the spec but not implemented here.
+/// A stream starts in the handshake phase. Once the key exchange completes, call
+/// [`ContextedStream::secure`] with the derived [`Session`](crate::session::Session) to
+/// switch both directions to the secure framing.
 ///
 /// ## Type Parameters
 ///
@@ -28,15 +29,152 @@
 /// ctx.write_message(client_hello)?;
 /// let msg: ClientHello = ctx.read_message()?;
 /// ```
-pub struct ContextedStream<S: std::io::Read + std::io::Write, C: crate::codec::Codec> {
+pub struct ContextedStream<S: std::io::Read + std::io::Write, C: crate::codec::CodecOwned> {
     stream: S,
+    secure: Option<SecureState>,
     _marker: std::marker::PhantomData<C>,
 }
 
+type Aes128Ctr = ctr::Ctr128BE<aes::Aes128>;
+
+/// Cipher state of a stream in the secure phase, one [`DirectionState`] per direction.
+struct SecureState {
+    outbound: DirectionState,
+    inbound: DirectionState,
+}
+
+/// Cipher state of one direction of the secure channel.
+///
+/// Each direction runs its own AES-128-CTR keystream, initialized from the session's
+/// `encryption_key` and `iv`; the keystream continues across frames, so both peers must
+/// process the direction's frames in the same order. `sequence` counts the frames
+/// processed so far. Each direction also has its own integrity algorithm, keyed with the
+/// direction's `integrity_key`.
+struct DirectionState {
+    cipher: Aes128Ctr,
+    integrity: Box<dyn crate::integrity::Integrity + Send>,
+    sequence: u64,
+    desynced: bool,
+}
+
+impl DirectionState {
+    fn new(
+        keys: &crate::session::DirectionKeys,
+        integrity: Box<dyn crate::integrity::Integrity + Send>,
+    ) -> Self {
+        use ctr::cipher::KeyIvInit;
+
+        DirectionState {
+            cipher: Aes128Ctr::new(keys.encryption_key().into(), keys.iv().into()),
+            integrity,
+            sequence: 0,
+            desynced: false,
+        }
+    }
+
+    /// Encrypts an outgoing packet in place and appends its checksum.
+    ///
+    /// The packet is `frame[offset..]`, so a header written in front of it stays plaintext.
+    /// Sealing advances the keystream, so the frame must then be sent in full: call
+    /// [`DirectionState::sent`] with the outcome of writing it.
+    fn seal(&mut self, frame: &mut Vec<u8>, offset: usize) -> Result<(), FrameError> {
+        use ctr::cipher::StreamCipher;
+
+        if self.desynced {
+            return Err(FrameError::Desync {
+                sequence: self.sequence,
+            });
+        }
+
+        let checksum = self.integrity.compute(&frame[offset..]);
+        self.cipher.apply_keystream(&mut frame[offset..]);
+        frame.extend_from_slice(&checksum);
+        Ok(())
+    }
+
+    /// Records whether a sealed frame was written.
+    ///
+    /// A failed or short write leaves the peer's keystream behind ours, so the direction
+    /// cannot recover and every later frame is rejected before being sealed.
+    fn sent(&mut self, written: bool) {
+        if written {
+            self.sequence += 1;
+        } else {
+            self.desynced = true;
+        }
+    }
+
+    /// Decrypts an incoming frame in place, strips its checksum and verifies it.
+    ///
+    /// With a continuous keystream a frame that was corrupted, replayed, reordered or
+    /// follows a dropped frame decrypts to garbage and fails the integrity check
+    /// ([`FrameError::ChecksumMismatch`]). The direction cannot recover from that, so every
+    /// later frame is rejected as well ([`FrameError::Desync`]).
+    fn open(&mut self, frame: &mut Vec<u8>) -> Result<(), FrameError> {
+        use ctr::cipher::StreamCipher;
+
+        if self.desynced {
+            return Err(FrameError::Desync {
+                sequence: self.sequence,
+            });
+        }
+
+        let payload_len = frame
+            .len()
+            .checked_sub(self.integrity.tag_len())
+            .ok_or(FrameError::Truncated)?;
+        let checksum = frame.split_off(payload_len);
+        self.cipher.apply_keystream(frame);
+
+        if !self.integrity.verify(frame, &checksum) {
+            self.desynced = true;
+            return Err(FrameError::ChecksumMismatch);
+        }
+        self.sequence += 1;
+        Ok(())
+    }
+}
+
+/// Errors specific to the secure-phase framing.
+///
+/// Returned wrapped in a `std::io::Error` of kind `InvalidData`; use
+/// `std::io::Error::get_ref` and `downcast_ref` to inspect it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FrameError {
+    /// The frame is too short to contain the negotiated checksum.
+    Truncated,
+    /// The checksum does not match the decrypted packet: the frame was corrupted, replayed
+    /// or reordered, or follows a dropped frame.
+    ChecksumMismatch,
+    /// The direction's keystream is out of step with the peer, so the frame is rejected
+    /// without being processed: an earlier inbound frame failed the integrity check, or an
+    /// earlier outbound frame failed to be written. `sequence` is the zero-based index of that
+    /// frame in its direction.
+    ///
+    /// Inbound desynchronization is only detected by the integrity check. With
+    /// `Checksum::None` every frame verifies, so a corrupted, replayed, reordered or dropped
+    /// frame is decrypted to garbage and passed to the codec instead.
+    Desync { sequence: u64 },
+}
+
+impl std::fmt::Display for FrameError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            FrameError::Truncated => write!(f, "Frame too short for checksum"),
+            FrameError::ChecksumMismatch => write!(f, "Frame checksum mismatch"),
+            FrameError::Desync { sequence } => {
+                write!(f, "Secure channel desynchronized at frame {}", sequence)
+            }
+        }
+    }
+}
+
+impl std::error::Error for FrameError {}
+
 impl<T, C> std::ops::Deref for ContextedStream<T, C>
 where
     T: std::io::Read + std::io::Write,
-    C: crate::codec::Codec,
+    C: crate::codec::CodecOwned,
 {
     type Target = T;
     fn deref(&self) -> &Self::Target {
@@ -47,7 +185,7 @@ where
 impl<T, C> std::ops::DerefMut for ContextedStream<T, C>
 where
     T: std::io::Read + std::io::Write,
-    C: crate::codec::Codec,
+    C: crate::codec::CodecOwned,
 {
     fn deref_mut(&mut self) -> &mut Self::Target {
         &mut self.stream
@@ -57,13 +195,71 @@ where
 impl<S, C> ContextedStream<S, C>
 where
     S: std::io::Read + std::io::Write,
-    C: crate::codec::Codec,
+    C: crate::codec::CodecOwned,
 {
+    /// Switches the stream to the secure phase.
+    ///
+    /// All subsequent frames are encrypted with the session's outbound keys and decrypted
+    /// with its inbound keys. `checksum` is the integrity protection negotiated in
+    /// `ServerHello`; it is resolved with each direction's integrity key.
+    ///
+    /// `Checksum::None` provides no integrity: tampered or out-of-order frames are not
+    /// detected ([`FrameError::ChecksumMismatch`] and [`FrameError::Desync`] never occur on
+    /// reads) and decrypt to garbage, which the codec may or may not reject.
+    ///
+    /// Arguments:
+    /// - `session`: Keys derived from the handshake key exchange.
+    /// - `checksum`: Negotiated checksum (NoOp, CRC16, HMAC-SHA256(4..=32)).
+    ///
+    /// ## Errors
+    ///
+    /// Returns an error if the checksum size is outside the range allowed by the spec.
+    pub fn secure(
+        &mut self,
+        session: &crate::session::Session,
+        checksum: crate::message::Checksum,
+    ) -> std::io::Result<()> {
+        let resolve = |key| {
+            checksum
+                .integrity(key)
+                .map_err(|err| std::io::Error::new(std::io::ErrorKind::InvalidInput, err))
+        };
+        let outbound_integrity = resolve(session.outbound().integrity_key())?;
+        let inbound_integrity = resolve(session.inbound().integrity_key())?;
+        self.secure_with(session, outbound_integrity, inbound_integrity);
+        Ok(())
+    }
+
+    /// Switches the stream to the secure phase with custom integrity algorithms.
+    ///
+    /// Like [`ContextedStream::secure`], but the tags appended to outgoing frames and
+    /// verified on incoming frames come from the given implementations.
+    ///
+    /// Arguments:
+    /// - `session`: Keys derived from the handshake key exchange.
+    /// - `outbound_integrity`: Algorithm tagging frames we send.
+    /// - `inbound_integrity`: Algorithm verifying frames we receive.
+    pub fn secure_with(
+        &mut self,
+        session: &crate::session::Session,
+        outbound_integrity: Box<dyn crate::integrity::Integrity + Send>,
+        inbound_integrity: Box<dyn crate::integrity::Integrity + Send>,
+    ) {
+        self.secure = Some(SecureState {
+            outbound: DirectionState::new(session.outbound(), outbound_integrity),
+            inbound: DirectionState::new(session.inbound(), inbound_integrity),
+        });
+    }
+
+    /// Returns `true` once the stream has been switched to the secure phase.
+    pub fn is_secure(&self) -> bool {
+        self.secure.is_some()
+    }
+
     /// Reads a single codec message from the stream and converts it to the target type.
     ///
-    /// The message is expected to be framed as: `[length: i16 LE, payload...]` where
-    /// `length` includes the 2-byte length prefix itself. The payload is decoded as
-    /// codec type `C` and then converted to the target type `T` via `TryFrom`.
+    /// The frame is read with [`ContextedStream::read_frame`], decoded as codec type `C`
+    /// and then converted to the target type `T` via `TryFrom`.
     ///
     /// ## Type Parameters
     ///
@@ -72,29 +268,73 @@ where
     /// ## Errors
     ///
     /// Returns an error if:
-    /// - Reading from the stream fails (I/O error).
-    /// - The length field is invalid or negative.
+    /// - Reading the frame fails (see [`ContextedStream::read_frame`]).
     /// - The codec decode fails (unknown opcode, malformed data).
     /// - The conversion from codec to target type fails.
     pub fn read_message<T: TryFrom<C>>(&mut self) -> std::io::Result<T> {
+        let buffer = self.read_frame()?;
+        C::decode(&buffer)?.try_into().map_err(|_| {
+            std::io::Error::new(std::io::ErrorKind::InvalidData, "Failed to convert message")
+        })
+    }
+
+    /// Reads a single frame from the stream and returns its payload, undecoded.
+    ///
+    /// The frame is expected to be framed as: `[length: i16 LE, payload...]` where
+    /// `length` includes the 2-byte length prefix itself. In the secure phase the payload
+    /// is followed by the negotiated checksum; the payload is decrypted and the checksum
+    /// verified before it is returned.
+    ///
+    /// Decoding the payload with a codec of borrowed messages yields views into it instead
+    /// of copies, which `read_message` cannot return since it drops the payload.
+    ///
+    /// ## Examples
+    ///
+    /// ```ignore
+    /// let frame = ctx.read_frame()?;
+    /// match Traffic::decode(&frame)? {
+    ///     Traffic::Chat(chat) => println!("{}", chat.text),
+    ///     _ => {}
+    /// }
+    /// ```
+    ///
+    /// ## Errors
+    ///
+    /// Returns an error if:
+    /// - Reading from the stream fails (I/O error).
+    /// - The length field is invalid or negative.
+    /// - The checksum does not match, or an earlier one did and the channel is
+    ///   desynchronized ([`FrameError`]).
+    pub fn read_frame(&mut self) -> std::io::Result<Vec<u8>> {
         let mut length_bytes = [0u8; 2];
         self.read_exact(&mut length_bytes)?;
-        let length: usize = i16::from_le_bytes(length_bytes).try_into().map_err(|_| {
-            std::io::Error::new(std::io::ErrorKind::InvalidData, "Invalid message length")
-        })?;
+        let length: usize = i16::from_le_bytes(length_bytes)
+            .checked_sub(2)
+            .and_then(|length| length.try_into().ok())
+            .ok_or_else(|| {
+                std::io::Error::new(std::io::ErrorKind::InvalidData, "Invalid message length")
+            })?;
 
-        let mut buffer = vec![0u8; length - 2];
+        let mut buffer = vec![0u8; length];
         self.read_exact(&mut buffer)?;
 
-        C::decode(&buffer)?.try_into().map_err(|_| {
-            std::io::Error::new(std::io::ErrorKind::InvalidData, "Failed to convert message")
-        })
+        if let Some(secure) = &mut self.secure {
+            secure
+                .inbound
+                .open(&mut buffer)
+                .map_err(|err| std::io::Error::new(std::io::ErrorKind::InvalidData, err))?;
+        }
+
+        Ok(buffer)
     }
 
     /// Writes a message to the stream with length-prefixed framing.
     ///
     /// Encodes the message using the codec and prefixes it with a 2-byte little-endian
-    /// length field (including the length field itself). The message is then written to the stream.
+    /// length field (including the length field itself). In the secure phase the encoded
+    /// message is encrypted and followed by the negotiated checksum of its plaintext.
+    /// The frame is assembled in a single buffer of its exact size, sized by
+    /// [`Codec::encoded_len`](crate::codec::Codec::encoded_len), and written with one call.
     ///
     /// ## Type Parameters
     ///
@@ -103,17 +343,46 @@ where
     /// ## Errors
     ///
     /// Returns an error if:
-    /// - Writing to the stream fails (I/O error).
+    /// - Writing to the stream fails (I/O error). In the secure phase the frame was already
+    ///   encrypted, so the outbound direction is desynchronized and every later write fails
+    ///   with [`FrameError::Desync`]; reconnect instead of retrying.
     /// - The encoded message exceeds the maximum representable length (32767 bytes).
-    /// - Codec encoding fails.
+    /// - Codec encoding fails, or writes a different number of bytes than its
+    ///   `encoded_len()` announced.
     pub fn write_message(&mut self, message: impl Into<C>) -> std::io::Result<()> {
-        let encoded = message.into().encode()?;
-        let length: i16 = encoded.len().try_into().map_err(|_| {
-            std::io::Error::new(std::io::ErrorKind::InvalidData, "Message too large")
-        })?;
-        self.write_all(&(length + 2).to_le_bytes())?;
-        self.write_all(&encoded)?;
-        Ok(())
+        let message = message.into();
+        let encoded_len = message.encoded_len();
+        let checksum_len = self
+            .secure
+            .as_ref()
+            .map_or(0, |secure| secure.outbound.integrity.tag_len());
+        let frame_len = encoded_len
+            .checked_add(checksum_len + 2)
+            .filter(|&length| length <= i16::MAX as usize)
+            .ok_or_else(|| {
+                std::io::Error::new(std::io::ErrorKind::InvalidData, "Message too large")
+            })?;
+
+        let mut frame = Vec::with_capacity(frame_len);
+        frame.extend_from_slice(&(frame_len as i16).to_le_bytes());
+        message.encode_into(&mut frame)?;
+        if frame.len() != 2 + encoded_len {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidData,
+                "Encoded message length does not match encoded_len",
+            ));
+        }
+
+        let Some(secure) = &mut self.secure else {
+            return self.stream.write_all(&frame);
+        };
+        secure
+            .outbound
+            .seal(&mut frame, 2)
+            .map_err(|err| std::io::Error::new(std::io::ErrorKind::InvalidData, err))?;
+        let result = self.stream.write_all(&frame);
+        secure.outbound.sent(result.is_ok());
+        result
     }
 }
 
@@ -134,12 +403,13 @@ pub trait WithContext: std::io::Read + std::io::Write {
     /// let stream = TcpStream::connect("127.0.0.1:2106")?;
     /// let mut ctx = stream.with_context::<Login>();
     /// ```
-    fn with_context<C: crate::codec::Codec>(self) -> ContextedStream<Self, C>
+    fn with_context<C: crate::codec::CodecOwned>(self) -> ContextedStream<Self, C>
     where
         Self: Sized,
     {
         ContextedStream {
             stream: self,
+            secure: None,
             _marker: std::marker::PhantomData,
         }
     }
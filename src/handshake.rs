@@ -0,0 +1,300 @@
+use crate::{
+    codec::Login,
+    context::ContextedStream,
+    identity::{ServerIdentity, TrustError, TrustStore},
+    message::{sec1_i16::MalformedPublicKey, Checksum, ClientHello, ClientReady},
+    replay::ReplayCache,
+    session::{Role, Session},
+};
+
+/// Errors returned when a handshake step fails validation.
+///
+/// Returned wrapped in a `std::io::Error` of kind `InvalidData`; use
+/// `std::io::Error::get_ref` and `downcast_ref` to inspect it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum HandshakeError {
+    /// The peer sent a message other than the one expected at this step.
+    UnexpectedMessage,
+    /// The `ClientHello` timestamp could not be recovered.
+    InvalidTimestamp,
+    /// The `ClientHello` timestamp is outside the allowed clock skew.
+    TimestampOutOfWindow,
+    /// The `ClientHello` was already seen by the replay cache, or the cache is full.
+    Replayed,
+    /// The peer's public key is not a valid P-256 SEC1 public key.
+    MalformedPublicKey,
+    /// The negotiated checksum size is not allowed by the spec.
+    InvalidChecksum,
+    /// The `ServerHello` signature was rejected by the trust store.
+    Untrusted(TrustError),
+}
+
+impl std::fmt::Display for HandshakeError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            HandshakeError::UnexpectedMessage => write!(f, "Unexpected handshake message"),
+            HandshakeError::InvalidTimestamp => write!(f, "Invalid client timestamp"),
+            HandshakeError::TimestampOutOfWindow => {
+                write!(f, "Client timestamp outside allowed clock skew")
+            }
+            HandshakeError::Replayed => write!(f, "Replayed client hello"),
+            HandshakeError::MalformedPublicKey => write!(f, "Malformed peer public key"),
+            HandshakeError::InvalidChecksum => write!(f, "Invalid checksum size"),
+            HandshakeError::Untrusted(err) => write!(f, "Untrusted server: {}", err),
+        }
+    }
+}
+
+impl std::error::Error for HandshakeError {}
+
+impl From<HandshakeError> for std::io::Error {
+    fn from(err: HandshakeError) -> Self {
+        std::io::Error::new(std::io::ErrorKind::InvalidData, err)
+    }
+}
+
+/// Client-side handshake parameters.
+///
+/// Fields:
+/// - `primary_obfuscation_value` (spec: key1): `ClientHello` obfuscation constant.
+/// - `secondary_obfuscation_value` (spec: key2): `ClientHello` obfuscation constant.
+/// - `trust_store`: pinned server keys used to authenticate `ServerHello`.
+/// - `rng`: cryptographically secure RNG for the random key and the client key pair.
+pub struct ClientConfig<R> {
+    primary_obfuscation_value: i64,
+    secondary_obfuscation_value: i64,
+    trust_store: TrustStore,
+    rng: R,
+}
+
+impl<R: p256::elliptic_curve::rand_core::CryptoRngCore> ClientConfig<R> {
+    /// Creates a new client configuration.
+    pub fn new(
+        primary_obfuscation_value: i64,
+        secondary_obfuscation_value: i64,
+        trust_store: TrustStore,
+        rng: R,
+    ) -> Self {
+        ClientConfig {
+            primary_obfuscation_value,
+            secondary_obfuscation_value,
+            trust_store,
+            rng,
+        }
+    }
+
+    /// Returns the trust store, e.g. to rotate pinned keys between connections.
+    pub fn trust_store_mut(&mut self) -> &mut TrustStore {
+        &mut self.trust_store
+    }
+}
+
+/// Server-side handshake parameters.
+///
+/// Fields:
+/// - `primary_obfuscation_value` (spec: key1): `ClientHello` obfuscation constant.
+/// - `secondary_obfuscation_value` (spec: key2): `ClientHello` obfuscation constant.
+/// - `identity`: long-term identity signing each connection's ephemeral key.
+/// - `checksum`: integrity policy announced in `ServerHello`.
+/// - `max_clock_skew`: largest accepted difference between the `ClientHello` timestamp
+///   and the server clock (default 30 seconds).
+/// - `replay_cache`: optional store of accepted `ClientHello` values (default none).
+/// - `rng`: cryptographically secure RNG for the ephemeral key pairs.
+pub struct ServerConfig<R> {
+    primary_obfuscation_value: i64,
+    secondary_obfuscation_value: i64,
+    identity: ServerIdentity,
+    checksum: Checksum,
+    max_clock_skew: std::time::Duration,
+    replay_cache: Option<std::sync::Arc<dyn ReplayCache>>,
+    rng: R,
+}
+
+impl<R: p256::elliptic_curve::rand_core::CryptoRngCore> ServerConfig<R> {
+    /// Creates a new server configuration with the default clock skew window.
+    pub fn new(
+        primary_obfuscation_value: i64,
+        secondary_obfuscation_value: i64,
+        identity: ServerIdentity,
+        checksum: Checksum,
+        rng: R,
+    ) -> Self {
+        ServerConfig {
+            primary_obfuscation_value,
+            secondary_obfuscation_value,
+            identity,
+            checksum,
+            max_clock_skew: std::time::Duration::from_secs(30),
+            replay_cache: None,
+            rng,
+        }
+    }
+
+    /// Sets the largest accepted clock skew and returns the configuration.
+    pub fn with_max_clock_skew(mut self, max_clock_skew: std::time::Duration) -> Self {
+        self.max_clock_skew = max_clock_skew;
+        self
+    }
+
+    /// Sets the replay cache consulted for every `ClientHello` and returns the
+    /// configuration.
+    ///
+    /// The cache is shared, so one cache can protect several configurations or threads.
+    pub fn with_replay_cache(mut self, replay_cache: std::sync::Arc<dyn ReplayCache>) -> Self {
+        self.replay_cache = Some(replay_cache);
+        self
+    }
+}
+
+/// Runs the client side of the login handshake.
+///
+/// Sequence:
+/// - Sends `ClientHello` with a random key and the current time, obfuscated with the
+///   configured values.
+/// - Reads `ServerHello` and authenticates it against the trust store.
+/// - Sends `ClientReady` with a fresh P-256 public key.
+/// - Derives the session keys and switches the stream to the secure framing with the
+///   checksum negotiated in `ServerHello`.
+///
+/// ## Examples
+///
+/// ```ignore
+/// use pokemmo::handshake::{self, ClientConfig};
+///
+/// let config = ClientConfig::new(key1, key2, trust_store, OsRng);
+/// let stream = TcpStream::connect("127.0.0.1:2106")?.with_context::<Login>();
+/// let mut stream = handshake::client(stream, &mut config)?;
+/// ```
+///
+/// ## Errors
+///
+/// Returns an error if:
+/// - Reading from or writing to the stream fails (I/O error).
+/// - The server replies with anything other than a valid, trusted `ServerHello`
+///   ([`HandshakeError`]).
+pub fn client<S, R>(
+    mut stream: ContextedStream<S, Login>,
+    config: &mut ClientConfig<R>,
+) -> std::io::Result<ContextedStream<S, Login>>
+where
+    S: std::io::Read + std::io::Write,
+    R: p256::elliptic_curve::rand_core::CryptoRngCore,
+{
+    let client_hello = ClientHello::new(
+        config.rng.next_u64() as i64,
+        std::time::SystemTime::now(),
+        config.primary_obfuscation_value,
+        config.secondary_obfuscation_value,
+    )
+    .map_err(|err| std::io::Error::new(std::io::ErrorKind::InvalidInput, err))?;
+    stream.write_message(client_hello)?;
+
+    let server_hello = match read_login(&mut stream)? {
+        Login::ServerHello(server_hello) => server_hello,
+        _ => return Err(HandshakeError::UnexpectedMessage.into()),
+    };
+    let server_public_key = config
+        .trust_store
+        .verify(&server_hello)
+        .map_err(HandshakeError::Untrusted)?;
+    let checksum = server_hello
+        .checksum()
+        .map_err(|_| HandshakeError::InvalidChecksum)?;
+
+    let client_secret_key = p256::SecretKey::random(&mut config.rng);
+    stream.write_message(ClientReady::new(client_secret_key.public_key()))?;
+
+    let session = Session::derive(Role::Client, &client_secret_key, &server_public_key);
+    stream.secure(&session, checksum)?;
+    Ok(stream)
+}
+
+/// Runs the server side of the login handshake.
+///
+/// Sequence:
+/// - Reads `ClientHello`, de-obfuscates it, checks its timestamp against the allowed
+///   clock skew and, if configured, rejects it if the replay cache has seen it before.
+/// - Sends a `ServerHello` carrying a fresh ephemeral key signed by the identity key and
+///   the configured checksum.
+/// - Reads `ClientReady` with the client's public key.
+/// - Derives the session keys and switches the stream to the secure framing.
+///
+/// ## Examples
+///
+/// ```ignore
+/// use pokemmo::handshake::{self, ServerConfig};
+///
+/// let mut config = ServerConfig::new(key1, key2, identity, Checksum::Crc16, OsRng);
+/// let (stream, _) = listener.accept()?;
+/// let mut stream = handshake::server(stream.with_context::<Login>(), &mut config)?;
+/// ```
+///
+/// ## Errors
+///
+/// Returns an error if:
+/// - Reading from or writing to the stream fails (I/O error).
+/// - The client sends a malformed message (I/O error of kind `InvalidData`).
+/// - The client sends an unexpected message, a timestamp outside the allowed window, a
+///   replayed `ClientHello` or a malformed public key ([`HandshakeError`]).
+pub fn server<S, R>(
+    mut stream: ContextedStream<S, Login>,
+    config: &mut ServerConfig<R>,
+) -> std::io::Result<ContextedStream<S, Login>>
+where
+    S: std::io::Read + std::io::Write,
+    R: p256::elliptic_curve::rand_core::CryptoRngCore,
+{
+    let client_hello = match stream.read_message::<Login>()? {
+        Login::ClientHello(client_hello) => client_hello,
+        _ => return Err(HandshakeError::UnexpectedMessage.into()),
+    };
+    let timestamp = client_hello
+        .timestamp(
+            config.primary_obfuscation_value,
+            config.secondary_obfuscation_value,
+        )
+        .map_err(|_| HandshakeError::InvalidTimestamp)?;
+    let now = std::time::SystemTime::now();
+    let skew = now
+        .duration_since(timestamp)
+        .or_else(|_| timestamp.duration_since(now))
+        .map_err(|_| HandshakeError::InvalidTimestamp)?;
+    if skew > config.max_clock_skew {
+        return Err(HandshakeError::TimestampOutOfWindow.into());
+    }
+    if let Some(replay_cache) = &config.replay_cache {
+        let integrity = client_hello.integrity(config.primary_obfuscation_value);
+        if !replay_cache.insert(integrity, timestamp) {
+            return Err(HandshakeError::Replayed.into());
+        }
+    }
+
+    let (server_secret_key, server_hello) = config
+        .identity
+        .server_hello(&mut config.rng, config.checksum);
+    stream.write_message(server_hello)?;
+
+    let client_ready = match read_login(&mut stream)? {
+        Login::ClientReady(client_ready) => client_ready,
+        _ => return Err(HandshakeError::UnexpectedMessage.into()),
+    };
+    let client_public_key = client_ready.public_key();
+
+    let session = Session::derive(Role::Server, &server_secret_key, &client_public_key);
+    stream.secure(&session, config.checksum)?;
+    Ok(stream)
+}
+
+/// Reads the next handshake message, reporting a public key in it that fails to decode as
+/// [`HandshakeError::MalformedPublicKey`] rather than as a generic framing error.
+fn read_login<S>(stream: &mut ContextedStream<S, Login>) -> std::io::Result<Login>
+where
+    S: std::io::Read + std::io::Write,
+{
+    stream.read_message::<Login>().map_err(|err| {
+        match err.get_ref().and_then(|e| e.downcast_ref::<MalformedPublicKey>()) {
+            Some(_) => HandshakeError::MalformedPublicKey.into(),
+            None => err,
+        }
+    })
+}
@@ -0,0 +1,157 @@
+/// Errors returned when authenticating a `ServerHello`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TrustError {
+    /// The signature is not a valid DER-encoded ECDSA signature.
+    MalformedSignature,
+    /// No pinned key produced a valid signature over the server public key.
+    UntrustedSignature,
+}
+
+impl std::fmt::Display for TrustError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            TrustError::MalformedSignature => write!(f, "Malformed server signature"),
+            TrustError::UntrustedSignature => write!(f, "Server signature is not trusted"),
+        }
+    }
+}
+
+impl std::error::Error for TrustError {}
+
+/// Client-side store of pinned server verifying keys.
+///
+/// A `ServerHello` is accepted if any pinned key verifies its signature, so keys can be
+/// rotated by pinning the new key before the server switches to it and removing the old
+/// one afterwards.
+///
+/// ## Examples
+///
+/// ```ignore
+/// use pokemmo::identity::TrustStore;
+///
+/// let trust_store = TrustStore::new().with_key(current_key).with_key(next_key);
+/// let server_public_key = trust_store.verify(&server_hello)?;
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct TrustStore {
+    keys: Vec<p256::ecdsa::VerifyingKey>,
+}
+
+impl TrustStore {
+    /// Creates an empty trust store; it rejects every server until a key is pinned.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Pins `key` and returns the store, for chained construction.
+    pub fn with_key(mut self, key: p256::ecdsa::VerifyingKey) -> Self {
+        self.add(key);
+        self
+    }
+
+    /// Pins `key`. Pinning an already pinned key has no effect.
+    pub fn add(&mut self, key: p256::ecdsa::VerifyingKey) {
+        if !self.keys.contains(&key) {
+            self.keys.push(key);
+        }
+    }
+
+    /// Unpins `key`, returning whether it was pinned.
+    pub fn remove(&mut self, key: &p256::ecdsa::VerifyingKey) -> bool {
+        let len = self.keys.len();
+        self.keys.retain(|pinned| pinned != key);
+        self.keys.len() != len
+    }
+
+    /// Returns the pinned keys.
+    pub fn keys(&self) -> &[p256::ecdsa::VerifyingKey] {
+        &self.keys
+    }
+
+    /// Authenticates a `ServerHello` and returns the server public key it carries.
+    ///
+    /// The signature must verify over [`ServerHello::signed_data`] under one of the
+    /// pinned keys.
+    ///
+    /// ## Errors
+    ///
+    /// Returns a [`TrustError`] if the signature is malformed, or if no pinned key verifies
+    /// the signature.
+    ///
+    /// [`ServerHello::signed_data`]: crate::message::ServerHello::signed_data
+    pub fn verify(
+        &self,
+        server_hello: &crate::message::ServerHello,
+    ) -> Result<p256::PublicKey, TrustError> {
+        use p256::ecdsa::signature::Verifier;
+
+        let signature = server_hello
+            .signature()
+            .map_err(|_| TrustError::MalformedSignature)?;
+
+        let signed_data = server_hello.signed_data();
+        if self
+            .keys
+            .iter()
+            .any(|key| key.verify(&signed_data, &signature).is_ok())
+        {
+            Ok(server_hello.public_key())
+        } else {
+            Err(TrustError::UntrustedSignature)
+        }
+    }
+}
+
+/// Server-side long-term identity.
+///
+/// The identity key never takes part in the key exchange: each connection uses a fresh
+/// ephemeral P-256 key, and the identity key only signs that ephemeral public key. Clients
+/// pin [`ServerIdentity::verifying_key`] in their [`TrustStore`].
+///
+/// ## Examples
+///
+/// ```ignore
+/// use pokemmo::identity::ServerIdentity;
+/// use pokemmo::message::Checksum;
+///
+/// let identity = ServerIdentity::new(identity_key);
+/// let (ephemeral_secret_key, server_hello) = identity.server_hello(&mut OsRng, Checksum::Crc16);
+/// ```
+#[derive(Clone)]
+pub struct ServerIdentity {
+    signing_key: p256::ecdsa::SigningKey,
+}
+
+impl ServerIdentity {
+    /// Creates a server identity from its long-term ECDSA signing key.
+    pub fn new(signing_key: p256::ecdsa::SigningKey) -> Self {
+        ServerIdentity { signing_key }
+    }
+
+    /// Returns the verifying key clients should pin.
+    pub fn verifying_key(&self) -> &p256::ecdsa::VerifyingKey {
+        self.signing_key.verifying_key()
+    }
+
+    /// Generates a fresh ephemeral key for one connection and its signed `ServerHello`.
+    ///
+    /// Returns the ephemeral secret key, to be combined with the client's `ClientReady`
+    /// key, and the `ServerHello` to send.
+    ///
+    /// Arguments:
+    /// - `rng`: cryptographically secure random number generator.
+    /// - `checksum`: Negotiated checksum (NoOp, CRC16, HMAC-SHA256(4..=32)).
+    pub fn server_hello(
+        &self,
+        rng: &mut impl p256::elliptic_curve::rand_core::CryptoRngCore,
+        checksum: crate::message::Checksum,
+    ) -> (p256::SecretKey, crate::message::ServerHello) {
+        let ephemeral_secret_key = p256::SecretKey::random(rng);
+        let server_hello = crate::message::ServerHello::signed(
+            ephemeral_secret_key.public_key(),
+            &self.signing_key,
+            checksum,
+        );
+        (ephemeral_secret_key, server_hello)
+    }
+}
@@ -0,0 +1,189 @@
+/// An integrity algorithm protecting secure-phase frames.
+///
+/// The secure framing appends `compute(packet)` to every outgoing frame and calls
+/// `verify(packet, tag)` on every incoming one, where `packet` is the plaintext codec
+/// payload. Built-in algorithms are resolved from the negotiated [`Checksum`]; custom
+/// algorithms can be installed with [`ContextedStream::secure_with`].
+///
+/// Implementations must uphold two requirements that the framing does not check:
+/// - `compute` must return exactly `tag_len()` bytes for every input; the reader splits
+///   each frame at `tag_len()`, so any other length desyncs the peer.
+/// - `verify` must not leak through its timing how much of a forged tag was correct. The
+///   default implementation compares in constant time; overrides must do the same.
+///
+/// [`Checksum`]: crate::message::Checksum
+/// [`ContextedStream::secure_with`]: crate::context::ContextedStream::secure_with
+///
+/// ## Examples
+///
+/// ```ignore
+/// use pokemmo::integrity::Integrity;
+///
+/// struct Xor8;
+///
+/// impl Integrity for Xor8 {
+///     fn tag_len(&self) -> usize {
+///         1
+///     }
+///
+///     fn compute(&self, data: &[u8]) -> Vec<u8> {
+///         vec![data.iter().fold(0, |acc, byte| acc ^ byte)]
+///     }
+/// }
+/// ```
+pub trait Integrity {
+    /// Returns the number of tag bytes appended to each frame.
+    fn tag_len(&self) -> usize;
+
+    /// Computes the tag of a plaintext packet.
+    ///
+    /// The returned tag must be exactly `tag_len()` bytes long.
+    fn compute(&self, data: &[u8]) -> Vec<u8>;
+
+    /// Verifies the tag of a plaintext packet.
+    ///
+    /// The default implementation compares against `compute(data)` in constant time; only
+    /// the tag length may leak, which is public anyway.
+    fn verify(&self, data: &[u8], tag: &[u8]) -> bool {
+        constant_time_eq(&self.compute(data), tag)
+    }
+}
+
+/// Compares two byte slices without short-circuiting on the first differing byte.
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    if a.len() != b.len() {
+        return false;
+    }
+    let diff = a.iter().zip(b).fold(0u8, |acc, (x, y)| acc | (x ^ y));
+    std::hint::black_box(diff) == 0
+}
+
+/// No integrity protection (spec: NoOp); frames carry no tag.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct NoOp;
+
+impl Integrity for NoOp {
+    fn tag_len(&self) -> usize {
+        0
+    }
+
+    fn compute(&self, _data: &[u8]) -> Vec<u8> {
+        Vec::new()
+    }
+}
+
+/// CRC16 integrity (spec: CRC16); see [`crc16`].
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Crc16;
+
+impl Integrity for Crc16 {
+    fn tag_len(&self) -> usize {
+        2
+    }
+
+    fn compute(&self, data: &[u8]) -> Vec<u8> {
+        crc16(data).to_le_bytes().to_vec()
+    }
+}
+
+/// Truncated HMAC-SHA256 integrity (spec: HMAC-SHA256); see [`hmac_sha256`].
+#[derive(Clone)]
+pub struct HmacSha256 {
+    key: [u8; 32],
+    size: usize,
+}
+
+impl HmacSha256 {
+    /// Creates an HMAC-SHA256 integrity algorithm producing `size`-byte tags.
+    ///
+    /// Arguments:
+    /// - `key`: integrity key derived from the handshake.
+    /// - `size`: negotiated tag length (4..=32).
+    ///
+    /// ## Errors
+    ///
+    /// Fails if `size` is outside 4..=32.
+    pub fn new(key: [u8; 32], size: usize) -> Result<Self, &'static str> {
+        if !(4..=32).contains(&size) {
+            return Err("Invalid HMAC-SHA256 tag size");
+        }
+        Ok(HmacSha256 { key, size })
+    }
+}
+
+impl Integrity for HmacSha256 {
+    fn tag_len(&self) -> usize {
+        self.size
+    }
+
+    fn compute(&self, data: &[u8]) -> Vec<u8> {
+        hmac_sha256(&self.key, data, self.size)
+    }
+
+    fn verify(&self, data: &[u8], tag: &[u8]) -> bool {
+        tag.len() == self.size && verify_hmac_sha256(&self.key, data, tag)
+    }
+}
+
+/// Computes the CRC16 checksum used by the `Crc16` integrity mode.
+///
+/// Per pokemmo-spec this is CRC-16/CCITT-FALSE: polynomial `0x1021`, initial value
+/// `0xFFFF`, no input or output reflection and no final XOR. In the secure phase the
+/// checksum is computed over the plaintext packet and appended as 2 little-endian bytes.
+///
+/// ## Examples
+///
+/// ```ignore
+/// use pokemmo::integrity::crc16;
+///
+/// assert_eq!(crc16(b"123456789"), 0x29B1);
+/// ```
+pub fn crc16(data: &[u8]) -> u16 {
+    const POLYNOMIAL: u16 = 0x1021;
+
+    let mut crc: u16 = 0xFFFF;
+    for byte in data {
+        crc ^= (*byte as u16) << 8;
+        for _ in 0..8 {
+            crc = if crc & 0x8000 != 0 {
+                (crc << 1) ^ POLYNOMIAL
+            } else {
+                crc << 1
+            };
+        }
+    }
+    crc
+}
+
+/// Computes the truncated HMAC-SHA256 tag used by the `HmacSha256(size)` integrity mode.
+///
+/// The full 32-byte HMAC-SHA256 of `data` under `key` is truncated to its leftmost
+/// `size` bytes. In the secure phase the key is the direction's `integrity_key` from the
+/// handshake and the tag is computed over the plaintext packet.
+///
+/// Arguments:
+/// - `key`: integrity key derived from the handshake.
+/// - `data`: plaintext packet.
+/// - `size`: negotiated tag length, at most 32.
+pub fn hmac_sha256(key: &[u8], data: &[u8], size: usize) -> Vec<u8> {
+    use hmac::Mac;
+
+    let mut mac =
+        hmac::Hmac::<sha2::Sha256>::new_from_slice(key).expect("HMAC accepts keys of any length");
+    mac.update(data);
+    let tag = mac.finalize().into_bytes();
+    tag[..size.min(tag.len())].to_vec()
+}
+
+/// Verifies a truncated HMAC-SHA256 tag in constant time.
+///
+/// Returns `true` if `tag` equals the leftmost `tag.len()` bytes of the HMAC-SHA256 of
+/// `data` under `key`. Empty tags and tags longer than 32 bytes are rejected.
+pub fn verify_hmac_sha256(key: &[u8], data: &[u8], tag: &[u8]) -> bool {
+    use hmac::Mac;
+
+    let mut mac =
+        hmac::Hmac::<sha2::Sha256>::new_from_slice(key).expect("HMAC accepts keys of any length");
+    mac.update(data);
+    mac.verify_truncated_left(tag).is_ok()
+}
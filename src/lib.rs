@@ -1,6 +1,14 @@
+// Lets generated code refer to this crate as `::pokemmo` from inside it, too
+extern crate self as pokemmo;
+
 pub mod codec;
 pub mod message;
 pub mod context;
+pub mod handshake;
+pub mod identity;
+pub mod integrity;
+pub mod replay;
+pub mod session;
 
 // Re-export the Message derive macro
 pub use pokemmo_macros::Message;
@@ -2,9 +2,17 @@ mod client_hello;
 mod client_ready;
 mod server_hello;
 
+/// `#[with(sec1_i16)]` encoding of P-256 public keys, as in the handshake messages: SEC1
+/// uncompressed bytes, prefixed by their `i16` length.
+///
+/// Decoding rejects any other SEC1 form, so re-encoding a decoded key yields the transmitted
+/// bytes, e.g. for signature checks.
+pub mod sec1_i16;
+
 pub use self::client_hello::ClientHello;
 pub use self::client_ready::ClientReady;
 pub use self::server_hello::Checksum;
+pub use self::server_hello::HmacSize;
 pub use self::server_hello::ServerHello;
 
 /// The `Message` trait defines serialization and deserialization for protocol message payloads.
@@ -14,6 +22,13 @@ pub use self::server_hello::ServerHello;
 ///
 /// All primitive integer types are encoded as little-endian bytes. Variable-length fields
 /// like `Vec<u8>` require a length prefix, specified via the `#[prefixed(T)]` attribute.
+/// Fields of any other type are encoded through its [`Field`] implementation; every
+/// `Message` is a `Field` encoded inline, without a prefix.
+///
+/// The lifetime `'de` is that of the input being deserialized. A message with borrowed
+/// fields such as `&'a str` or `&'a [u8]` implements `Message<'de>` only for inputs that
+/// outlive it, and points into the input instead of copying it. A message that owns all its
+/// data implements `Message<'de>` for every `'de`, expressed as [`MessageOwned`].
 ///
 /// ## Examples
 ///
@@ -24,21 +39,44 @@ pub use self::server_hello::ServerHello;
 /// let bytes = msg.serialize()?;
 /// let decoded = ClientHello::deserialize(&bytes)?;
 /// ```
-pub trait Message: Sized {
+pub trait Message<'de>: Sized {
     /// Serializes this message into a byte vector.
     ///
     /// Integer fields are encoded as little-endian. Fields marked with `#[prefixed(T)]`
     /// are encoded as `[length_prefix, data...]` where `length_prefix` is of type `T`.
+    /// The vector is allocated once, sized by [`Message::encoded_len`].
     ///
     /// ## Errors
     ///
     /// Returns an error if I/O operations fail or data sizes exceed representable ranges.
-    fn serialize(&self) -> std::io::Result<Vec<u8>>;
+    fn serialize(&self) -> std::io::Result<Vec<u8>> {
+        let mut data = Vec::with_capacity(self.encoded_len());
+        self.serialize_into(&mut data)?;
+        Ok(data)
+    }
+
+    /// Serializes this message into a writer, without intermediate buffers.
+    ///
+    /// Writes exactly the bytes [`Message::serialize`] returns, so a message can be
+    /// appended to an existing buffer, e.g. behind a frame header.
+    ///
+    /// ## Errors
+    ///
+    /// Same as [`Message::serialize`], plus any error of the writer.
+    fn serialize_into(&self, writer: &mut impl std::io::Write) -> std::io::Result<()>;
+
+    /// Returns the number of bytes [`Message::serialize`] produces for this message.
+    ///
+    /// A message that fails to serialize (e.g. a prefix overflows) reports the length it
+    /// would have had; the error surfaces when serializing.
+    fn encoded_len(&self) -> usize;
 
     /// Deserializes a byte slice into this message type.
     ///
     /// The byte layout must match the structure expected by the implementation.
-    /// All fields are consumed in order; extra data is ignored.
+    /// All fields are consumed in order. Bytes left over after the last field are ignored,
+    /// unless the implementation captures them (`#[remainder]`) or rejects them
+    /// (`#[strict]`).
     ///
     /// ## Errors
     ///
@@ -46,7 +84,136 @@ pub trait Message: Sized {
     /// - The data is truncated and insufficient bytes remain.
     /// - A prefixed length exceeds the maximum allowed size.
     /// - Integer conversions fail (e.g., invalid byte sequences).
-    fn deserialize(data: &[u8]) -> std::io::Result<Self>;
+    /// - Bytes are left over and the implementation is strict.
+    fn deserialize(data: &'de [u8]) -> std::io::Result<Self> {
+        Self::deserialize_from(&mut std::io::Cursor::new(data))
+    }
+
+    /// Deserializes this message type from the current position of a cursor.
+    ///
+    /// On success the cursor is advanced past the consumed bytes, so messages can be
+    /// embedded in other messages and decoded in sequence.
+    ///
+    /// ## Errors
+    ///
+    /// Same as [`Message::deserialize`].
+    fn deserialize_from(rdr: &mut std::io::Cursor<&'de [u8]>) -> std::io::Result<Self>;
+}
+
+/// A [`Message`] that borrows nothing from its input, so it can be decoded from a
+/// temporary buffer and kept.
+///
+/// Implemented for every type implementing `Message<'de>` for all lifetimes `'de`; use it as
+/// a bound instead of the higher-ranked `for<'de> Message<'de>`.
+pub trait MessageOwned: for<'de> Message<'de> {}
+
+impl<T> MessageOwned for T where T: for<'de> Message<'de> {}
+
+/// The `Field` trait encodes a single field of a message.
+///
+/// `#[derive(Message)]` handles numbers, `bool`, arrays, strings and byte vectors itself and
+/// calls `Field` for any other field type, so implementing it lets a type be used as a field
+/// directly instead of being stored as raw bytes. Every [`Message`] is a `Field`.
+///
+/// For types of other crates, which cannot implement `Field` here, annotate the field with
+/// `#[with(path)]` instead, where `path` is a module with functions of the same shape:
+/// `fn encode(value: &T, writer: &mut impl std::io::Write) -> std::io::Result<()>` and
+/// `fn decode(rdr: &mut std::io::Cursor<&[u8]>) -> std::io::Result<T>`.
+///
+/// As with [`Message`], `'de` is the lifetime of the input; types that own their data
+/// implement `Field<'_>`.
+///
+/// ## Examples
+///
+/// ```ignore
+/// use pokemmo::message::Field;
+///
+/// pub struct TrainerId(u32);
+///
+/// impl Field<'_> for TrainerId {
+///     fn encode(&self, writer: &mut impl std::io::Write) -> std::io::Result<()> {
+///         writer.write_all(&self.0.to_le_bytes())
+///     }
+///
+///     fn decode(rdr: &mut std::io::Cursor<&[u8]>) -> std::io::Result<Self> {
+///         let mut bytes = [0u8; 4];
+///         std::io::Read::read_exact(rdr, &mut bytes)?;
+///         Ok(TrainerId(u32::from_le_bytes(bytes)))
+///     }
+/// }
+/// ```
+pub trait Field<'de>: Sized {
+    /// Writes the encoded field.
+    ///
+    /// ## Errors
+    ///
+    /// Returns an error if writing fails or the value cannot be represented.
+    fn encode(&self, writer: &mut impl std::io::Write) -> std::io::Result<()>;
+
+    /// Decodes the field from the current position of a cursor, advancing past it.
+    ///
+    /// ## Errors
+    ///
+    /// Returns an error if the data is truncated or malformed.
+    fn decode(rdr: &mut std::io::Cursor<&'de [u8]>) -> std::io::Result<Self>;
+
+    /// Returns the number of bytes [`Field::encode`] writes for this value.
+    ///
+    /// Named apart from [`Message::encoded_len`] so that calls stay unambiguous on messages,
+    /// which implement both traits. Defaults to encoding into a [`ByteCounter`]; override it
+    /// when the length is cheaper to compute.
+    fn encoded_size(&self) -> usize {
+        let mut counter = ByteCounter::default();
+        // A failing encode reports the bytes written so far; the error surfaces when encoding
+        let _ = self.encode(&mut counter);
+        counter.len()
+    }
+}
+
+impl<'de, T: Message<'de>> Field<'de> for T {
+    fn encode(&self, writer: &mut impl std::io::Write) -> std::io::Result<()> {
+        self.serialize_into(writer)
+    }
+
+    fn decode(rdr: &mut std::io::Cursor<&'de [u8]>) -> std::io::Result<Self> {
+        T::deserialize_from(rdr)
+    }
+
+    fn encoded_size(&self) -> usize {
+        Message::encoded_len(self)
+    }
+}
+
+/// A writer that discards its input and counts the bytes written.
+///
+/// Measures an encoding without buffering it, e.g. for [`Field::encoded_size`] or the
+/// length of a `#[with(path)]` field.
+#[derive(Debug, Default)]
+pub struct ByteCounter {
+    len: usize,
+}
+
+impl ByteCounter {
+    /// Returns the number of bytes written so far.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Returns `true` if nothing has been written.
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+}
+
+impl std::io::Write for ByteCounter {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.len += buf.len();
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
 }
 
 pub use pokemmo_macros::Message;
@@ -0,0 +1,59 @@
+/// Length of an uncompressed SEC1 P-256 point: 0x04 || x || y.
+const UNCOMPRESSED_LEN: usize = 65;
+
+/// Error returned when a public key is not an uncompressed P-256 SEC1 key.
+///
+/// Returned wrapped in a `std::io::Error` of kind `InvalidData`; use
+/// `std::io::Error::get_ref` and `downcast_ref` to inspect it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MalformedPublicKey;
+
+impl std::fmt::Display for MalformedPublicKey {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Malformed public key")
+    }
+}
+
+impl std::error::Error for MalformedPublicKey {}
+
+impl From<MalformedPublicKey> for std::io::Error {
+    fn from(err: MalformedPublicKey) -> Self {
+        std::io::Error::new(std::io::ErrorKind::InvalidData, err)
+    }
+}
+
+/// Writes `key` as its `i16` length followed by its uncompressed SEC1 bytes.
+///
+/// ## Errors
+///
+/// Returns an error if writing fails.
+pub fn encode(key: &p256::PublicKey, writer: &mut impl std::io::Write) -> std::io::Result<()> {
+    use p256::elliptic_curve::sec1::ToEncodedPoint;
+
+    let point = key.to_encoded_point(false);
+    let size = point.len() as i16;
+    writer.write_all(&size.to_le_bytes())?;
+    writer.write_all(point.as_bytes())
+}
+
+/// Reads an `i16` length-prefixed uncompressed SEC1 public key.
+///
+/// ## Errors
+///
+/// Returns an error if the data is truncated, or a [`MalformedPublicKey`] if the length or
+/// the bytes are not those of an uncompressed P-256 point.
+pub fn decode(rdr: &mut std::io::Cursor<&[u8]>) -> std::io::Result<p256::PublicKey> {
+    use std::io::Read;
+
+    let mut size = [0u8; 2];
+    rdr.read_exact(&mut size)?;
+    let mut bytes = [0u8; UNCOMPRESSED_LEN];
+    if i16::from_le_bytes(size) != UNCOMPRESSED_LEN as i16 {
+        return Err(MalformedPublicKey.into());
+    }
+    rdr.read_exact(&mut bytes)?;
+    if bytes[0] != 0x04 {
+        return Err(MalformedPublicKey.into());
+    }
+    p256::PublicKey::from_sec1_bytes(&bytes).map_err(|_| MalformedPublicKey.into())
+}
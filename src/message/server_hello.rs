@@ -12,8 +12,8 @@ use super::Message;
 /// - Checksum size mapping per spec: NoOp=0, CRC16=2, HMAC-SHA256=4..=32 (default 16).
 #[derive(Message)]
 pub struct ServerHello {
-    #[prefixed(i16)]
-    public_key: Vec<u8>,
+    #[with(super::sec1_i16)]
+    public_key: p256::PublicKey,
     #[prefixed(i16)]
     signature: Vec<u8>,
     checksum_size: i8,
@@ -32,19 +32,44 @@ impl ServerHello {
         checksum: Checksum,
     ) -> Self {
         ServerHello {
-            public_key: public_key.to_sec1_bytes().to_vec(),
+            public_key,
             signature: signature.to_der().as_bytes().to_vec(),
             checksum_size: checksum.into(),
         }
     }
 
-    /// Parses the server's public key from its SEC1-encoded bytes.
+    /// Creates a new `ServerHello` signed by the server's long-term identity key.
     ///
-    /// Errors:
-    /// - Fails if bytes are not a valid P-256 SEC1 public key.
-    pub fn public_key(&self) -> Result<p256::PublicKey, &'static str> {
-        p256::PublicKey::from_sec1_bytes(self.public_key.as_ref())
-            .map_err(|_| "Failed to parse public key from bytes")
+    /// Per pokemmo-spec, the server authenticates its per-connection ephemeral key by
+    /// signing the ephemeral public key (SEC1 uncompressed) with its identity key.
+    ///
+    /// Arguments:
+    /// - `public_key`: Server's ephemeral P-256 public key for this connection.
+    /// - `identity_key`: Server's long-term ECDSA signing key.
+    /// - `checksum`: Negotiated checksum (NoOp, CRC16, HMAC-SHA256(4..=32)).
+    pub fn signed(
+        public_key: p256::PublicKey,
+        identity_key: &p256::ecdsa::SigningKey,
+        checksum: Checksum,
+    ) -> Self {
+        use p256::ecdsa::signature::Signer;
+
+        let signature: p256::ecdsa::Signature = identity_key.sign(&public_key.to_sec1_bytes());
+        Self::new(public_key, signature, checksum)
+    }
+
+    /// Returns the server's public key.
+    pub fn public_key(&self) -> p256::PublicKey {
+        self.public_key
+    }
+
+    /// Returns the data covered by the signature.
+    ///
+    /// Per pokemmo-spec, the signature is computed over the server public key exactly as
+    /// transmitted (SEC1 uncompressed bytes, without the length prefix). Decoding only
+    /// accepts that form, so re-encoding the key reproduces the transmitted bytes.
+    pub fn signed_data(&self) -> Vec<u8> {
+        self.public_key.to_sec1_bytes().to_vec()
     }
 
     /// Parses the server's signature from its DER-encoded bytes.
@@ -66,17 +91,75 @@ impl ServerHello {
     }
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 /// Message integrity configuration negotiated during handshake.
 ///
 /// Spec mapping:
 /// - `None`: No checksum (`checksum_size = 0`)
 /// - `Crc16`: CRC16 (`checksum_size = 2`)
-/// - `HmacSha256(size)`: HMAC-SHA256 with `size` in 4..=32 (`checksum_size = size`)
+/// - `HmacSha256(size)`: HMAC-SHA256 with `size` in 4..=32 (`checksum_size = size`), built
+///   with [`Checksum::hmac`]
+///
+/// The checksum size always equals the tag length of the resolved
+/// [`Integrity`](crate::integrity::Integrity) implementation. Every value encodes to a size
+/// byte that decodes to the same configuration.
 pub enum Checksum {
     None,
     Crc16,
-    HmacSha256(i8),
+    HmacSha256(HmacSize),
+}
+
+/// Tag length of an HMAC-SHA256 [`Checksum`] in bytes, always within 4..=32.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct HmacSize(i8);
+
+impl HmacSize {
+    /// Returns the tag length in bytes.
+    pub fn get(self) -> i8 {
+        self.0
+    }
+}
+
+impl Checksum {
+    /// Creates an HMAC-SHA256 configuration with `size`-byte tags.
+    ///
+    /// Returns `None` if `size` is outside 4..=32.
+    pub fn hmac(size: i8) -> Option<Self> {
+        (4..=32)
+            .contains(&size)
+            .then_some(Checksum::HmacSha256(HmacSize(size)))
+    }
+
+    /// Returns the encoded checksum size byte, which is also the per-frame tag length.
+    pub fn size(&self) -> i8 {
+        match self {
+            Checksum::None => 0,
+            Checksum::Crc16 => 2,
+            Checksum::HmacSha256(size) => size.get(),
+        }
+    }
+
+    /// Resolves this configuration to its integrity algorithm.
+    ///
+    /// Arguments:
+    /// - `key`: the direction's integrity key; ignored by unkeyed algorithms.
+    ///
+    /// ## Errors
+    ///
+    /// Fails if the algorithm rejects its parameters. Sizes are validated when a `Checksum`
+    /// is built, so this does not happen for the built-in algorithms.
+    pub fn integrity(
+        &self,
+        key: &[u8; 32],
+    ) -> Result<Box<dyn crate::integrity::Integrity + Send>, &'static str> {
+        Ok(match self {
+            Checksum::None => Box::new(crate::integrity::NoOp),
+            Checksum::Crc16 => Box::new(crate::integrity::Crc16),
+            Checksum::HmacSha256(size) => {
+                Box::new(crate::integrity::HmacSha256::new(*key, size.get() as usize)?)
+            }
+        })
+    }
 }
 
 impl TryFrom<i8> for Checksum {
@@ -84,19 +167,15 @@ impl TryFrom<i8> for Checksum {
     fn try_from(size: i8) -> Result<Self, Self::Error> {
         Ok(match size {
             0 => Checksum::None,
-            1 => Checksum::Crc16,
-            4..=32 => Checksum::HmacSha256(size),
+            2 => Checksum::Crc16,
+            4..=32 => Checksum::HmacSha256(HmacSize(size)),
             _ => return Err("Invalid checksum size"),
         })
     }
 }
 
-impl Into<i8> for Checksum {
-    fn into(self) -> i8 {
-        match self {
-            Checksum::None => 0,
-            Checksum::Crc16 => 1,
-            Checksum::HmacSha256(size) => size,
-        }
+impl From<Checksum> for i8 {
+    fn from(checksum: Checksum) -> i8 {
+        checksum.size()
     }
 }
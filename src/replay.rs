@@ -0,0 +1,110 @@
+/// A store of `ClientHello` values already accepted by a server.
+///
+/// The server handshake consults the cache after validating the `ClientHello` timestamp and
+/// rejects the connection if the `(integrity, timestamp)` pair was seen before, so a captured
+/// `ClientHello` cannot be replayed verbatim. Implementations are shared between connections
+/// and must be thread-safe.
+///
+/// ## Examples
+///
+/// ```ignore
+/// use pokemmo::replay::InMemoryReplayCache;
+///
+/// let cache = InMemoryReplayCache::new(10_000, std::time::Duration::from_secs(60));
+/// let config = ServerConfig::new(key1, key2, identity, Checksum::Crc16, OsRng)
+///     .with_replay_cache(std::sync::Arc::new(cache));
+/// ```
+pub trait ReplayCache: Send + Sync {
+    /// Records a `ClientHello` and returns whether it may be accepted.
+    ///
+    /// Returns `false` if the value was seen before, or if it cannot be recorded: a cache
+    /// that forgot a live entry to make room would let that entry be replayed, so
+    /// implementations must fail closed.
+    ///
+    /// Arguments:
+    /// - `integrity` (spec: random key): de-obfuscated random value.
+    /// - `timestamp`: de-obfuscated client timestamp.
+    fn insert(&self, integrity: i64, timestamp: std::time::SystemTime) -> bool;
+}
+
+/// Bounded, time-windowed in-memory [`ReplayCache`].
+///
+/// Entries are forgotten once they are older than `window` (measured on the server clock
+/// from insertion). The window should be at least twice the handshake's maximum clock
+/// skew, since a `ClientHello` outside the skew window is rejected before reaching the
+/// cache.
+///
+/// The cache fails closed: when `capacity` unexpired entries are held, new values are
+/// rejected rather than evicting live ones, which an attacker could otherwise flush out by
+/// sending `capacity` fresh `ClientHello`s. Size `capacity` to cover the expected handshake
+/// rate times `window`, e.g. 100 handshakes per second over 60 seconds needs 6000 entries.
+pub struct InMemoryReplayCache {
+    capacity: usize,
+    window: std::time::Duration,
+    state: std::sync::Mutex<InMemoryState>,
+}
+
+#[derive(Default)]
+struct InMemoryState {
+    order: std::collections::VecDeque<(std::time::Instant, (i64, std::time::SystemTime))>,
+    seen: std::collections::HashSet<(i64, std::time::SystemTime)>,
+}
+
+impl InMemoryReplayCache {
+    /// Creates an empty cache.
+    ///
+    /// Arguments:
+    /// - `capacity`: maximum number of remembered entries (at least one).
+    /// - `window`: how long an entry is remembered.
+    pub fn new(capacity: usize, window: std::time::Duration) -> Self {
+        InMemoryReplayCache {
+            capacity,
+            window,
+            state: std::sync::Mutex::new(InMemoryState::default()),
+        }
+    }
+
+    /// Returns the number of remembered entries.
+    pub fn len(&self) -> usize {
+        self.lock().seen.len()
+    }
+
+    /// Returns `true` if no entries are remembered.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, InMemoryState> {
+        // The state is consistent after every operation, so a poisoned lock is still usable.
+        self.state
+            .lock()
+            .unwrap_or_else(std::sync::PoisonError::into_inner)
+    }
+}
+
+impl ReplayCache for InMemoryReplayCache {
+    fn insert(&self, integrity: i64, timestamp: std::time::SystemTime) -> bool {
+        let now = std::time::Instant::now();
+        let key = (integrity, timestamp);
+        let mut state = self.lock();
+
+        while let Some((inserted_at, expired)) = state.order.front().copied() {
+            if now.duration_since(inserted_at) <= self.window {
+                break;
+            }
+            state.order.pop_front();
+            state.seen.remove(&expired);
+        }
+
+        if state.seen.contains(&key) {
+            return false;
+        }
+
+        if state.order.len() >= self.capacity.max(1) {
+            return false;
+        }
+        state.seen.insert(key);
+        state.order.push_back((now, key));
+        true
+    }
+}
@@ -0,0 +1,147 @@
+/// Which side of the handshake a [`Session`] was derived for.
+///
+/// The role decides which direction's keys are used for outgoing and incoming traffic.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Role {
+    Client,
+    Server,
+}
+
+/// Key material protecting a single direction of the secure channel.
+///
+/// Fields:
+/// - `encryption_key`: AES-128 key for the direction's keystream.
+/// - `iv`: initial counter block for the keystream.
+/// - `integrity_key`: key for keyed checksums (HMAC-SHA256).
+#[derive(Clone)]
+pub struct DirectionKeys {
+    encryption_key: [u8; 16],
+    iv: [u8; 16],
+    integrity_key: [u8; 32],
+}
+
+impl DirectionKeys {
+    /// Returns the AES-128 encryption key for this direction.
+    pub fn encryption_key(&self) -> &[u8; 16] {
+        &self.encryption_key
+    }
+
+    /// Returns the initial counter block for this direction.
+    pub fn iv(&self) -> &[u8; 16] {
+        &self.iv
+    }
+
+    /// Returns the integrity key for this direction.
+    pub fn integrity_key(&self) -> &[u8; 32] {
+        &self.integrity_key
+    }
+}
+
+/// Secret material shared by both peers once the handshake key exchange completes.
+///
+/// Per pokemmo-spec, the server's ephemeral public key (`ServerHello`) and the client's
+/// public key (`ClientReady`) are combined with P-256 ECDH. The 32-byte shared secret
+/// (affine x-coordinate) is fed to HKDF-SHA256 without salt, and each key is expanded
+/// with its own label:
+/// - `client write key` / `server write key`: 16-byte encryption keys
+/// - `client write iv` / `server write iv`: 16-byte initial counter blocks
+/// - `client integrity key` / `server integrity key`: 32-byte integrity keys
+///
+/// "client write" keys protect client→server traffic, "server write" keys protect
+/// server→client traffic. Both peers derive identical material.
+///
+/// ## Examples
+///
+/// ```ignore
+/// use pokemmo::session::{Role, Session};
+///
+/// let server_public_key = server_hello.public_key();
+/// let session = Session::derive(Role::Client, &client_secret_key, &server_public_key);
+/// ```
+#[derive(Clone)]
+pub struct Session {
+    role: Role,
+    client_to_server: DirectionKeys,
+    server_to_client: DirectionKeys,
+}
+
+impl Session {
+    /// Derives the session keys from our secret key and the peer's public key.
+    ///
+    /// Arguments:
+    /// - `role`: the side of the handshake `secret_key` belongs to.
+    /// - `secret_key`: our P-256 secret key (the client key, or the server's ephemeral key).
+    /// - `peer_public_key`: the public key received from the peer.
+    pub fn derive(
+        role: Role,
+        secret_key: &p256::SecretKey,
+        peer_public_key: &p256::PublicKey,
+    ) -> Self {
+        let shared_secret = p256::ecdh::diffie_hellman(
+            secret_key.to_nonzero_scalar(),
+            peer_public_key.as_affine(),
+        );
+        let hkdf = shared_secret.extract::<sha2::Sha256>(None);
+
+        let expand = |label: &[u8], okm: &mut [u8]| {
+            // Output lengths are fixed and far below the HKDF-SHA256 limit.
+            hkdf.expand(label, okm)
+                .expect("HKDF output length is within bounds");
+        };
+
+        let mut client_to_server = DirectionKeys {
+            encryption_key: [0u8; 16],
+            iv: [0u8; 16],
+            integrity_key: [0u8; 32],
+        };
+        expand(b"client write key", &mut client_to_server.encryption_key);
+        expand(b"client write iv", &mut client_to_server.iv);
+        expand(b"client integrity key", &mut client_to_server.integrity_key);
+
+        let mut server_to_client = DirectionKeys {
+            encryption_key: [0u8; 16],
+            iv: [0u8; 16],
+            integrity_key: [0u8; 32],
+        };
+        expand(b"server write key", &mut server_to_client.encryption_key);
+        expand(b"server write iv", &mut server_to_client.iv);
+        expand(b"server integrity key", &mut server_to_client.integrity_key);
+
+        Session {
+            role,
+            client_to_server,
+            server_to_client,
+        }
+    }
+
+    /// Returns the side of the handshake this session was derived for.
+    pub fn role(&self) -> Role {
+        self.role
+    }
+
+    /// Returns the keys protecting client→server traffic.
+    pub fn client_to_server(&self) -> &DirectionKeys {
+        &self.client_to_server
+    }
+
+    /// Returns the keys protecting server→client traffic.
+    pub fn server_to_client(&self) -> &DirectionKeys {
+        &self.server_to_client
+    }
+
+    /// Returns the keys for traffic we send.
+    pub fn outbound(&self) -> &DirectionKeys {
+        match self.role {
+            Role::Client => &self.client_to_server,
+            Role::Server => &self.server_to_client,
+        }
+    }
+
+    /// Returns the keys for traffic we receive.
+    pub fn inbound(&self) -> &DirectionKeys {
+        match self.role {
+            Role::Client => &self.server_to_client,
+            Role::Server => &self.client_to_server,
+        }
+    }
+}
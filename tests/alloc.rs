@@ -0,0 +1,58 @@
+use pokemmo::message::Message;
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::cell::Cell;
+
+/// Counts allocations made by the current thread, so that tests running in parallel don't
+/// interfere.
+struct CountingAllocator;
+
+thread_local! {
+    static ALLOCATIONS: Cell<usize> = const { Cell::new(0) };
+}
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATIONS.with(|count| count.set(count.get() + 1));
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+}
+
+#[global_allocator]
+static ALLOCATOR: CountingAllocator = CountingAllocator;
+
+fn allocations<T>(f: impl FnOnce() -> T) -> (T, usize) {
+    let before = ALLOCATIONS.with(Cell::get);
+    let result = f();
+    (result, ALLOCATIONS.with(Cell::get) - before)
+}
+
+#[derive(Message, Debug, PartialEq)]
+struct Strings {
+    #[prefixed(u8)]
+    utf8: String,
+    #[prefixed(u16, utf16)]
+    utf16: String,
+    #[prefixed(u16, utf16, bytes)]
+    utf16_bytes: String,
+}
+
+#[test]
+fn serialize_into_does_not_allocate() {
+    let msg = Strings {
+        utf8: "Pokémon".to_string(),
+        utf16: "Pokémon 🎮".to_string(),
+        utf16_bytes: "Ash".to_string(),
+    };
+    let mut buf = [0u8; 64];
+    let (len, count) = allocations(|| {
+        let mut writer = &mut buf[..];
+        msg.serialize_into(&mut writer).unwrap();
+        msg.encoded_len()
+    });
+    assert_eq!(count, 0);
+    assert_eq!(buf[..len], msg.serialize().unwrap());
+}
@@ -0,0 +1,148 @@
+use pokemmo::{
+    codec::{Codec, Login},
+    context::WithContext,
+    message::{ClientReady, Message},
+};
+
+#[derive(Message, Debug, PartialEq)]
+struct Whisper<'a> {
+    #[prefixed(u8)]
+    to: &'a str,
+    #[prefixed(i16)]
+    text: &'a str,
+}
+
+#[pokemmo::codec::codec]
+enum Traffic<'a> {
+    Whisper(Whisper<'a>) = 0x10u8,
+    Unknown { opcode: u8, data: &'a [u8] },
+}
+
+#[derive(Message, Debug, PartialEq)]
+struct Batch<T> {
+    #[count(u8)]
+    items: Vec<T>,
+}
+
+#[pokemmo::codec::codec]
+enum Envelope<T>
+where
+    T: std::fmt::Debug,
+{
+    Single(T) = 0x01u8,
+    Batch(Batch<T>) = 0x02u8,
+    Unknown { opcode: i8, data: Vec<u8> },
+}
+
+#[test]
+fn borrowed_codec_round_trips() {
+    let msg = Traffic::Whisper(Whisper {
+        to: "Misty",
+        text: "Trade?",
+    });
+    let bytes = msg.encode().unwrap();
+    assert_eq!(msg.encoded_len(), bytes.len());
+    assert_eq!(bytes[0], 0x10);
+    let Traffic::Whisper(whisper) = Traffic::decode(&bytes).unwrap() else {
+        panic!("wrong variant");
+    };
+    assert_eq!(whisper.text, "Trade?");
+    assert!(bytes.as_ptr_range().contains(&whisper.text.as_ptr()));
+
+    let bytes = [0x7F, 1, 2];
+    let Traffic::Unknown { opcode, data } = Traffic::decode(&bytes).unwrap() else {
+        panic!("wrong variant");
+    };
+    assert_eq!((opcode, data), (0x7F, &bytes[1..]));
+    assert_eq!(data.as_ptr(), bytes[1..].as_ptr());
+}
+
+#[test]
+fn frames_decode_into_views() {
+    let payload = Whisper {
+        to: "Brock",
+        text: "Hi",
+    }
+    .serialize()
+    .unwrap();
+    let mut stream = std::io::Cursor::new(Vec::new()).with_context::<Login>();
+    stream
+        .write_message(Login::Unknown {
+            opcode: 0x10,
+            data: payload,
+        })
+        .unwrap();
+    let key = p256::SecretKey::random(&mut p256::elliptic_curve::rand_core::OsRng).public_key();
+    stream.write_message(ClientReady::new(key)).unwrap();
+
+    let mut stream = std::io::Cursor::new(stream.get_ref().clone()).with_context::<Login>();
+    let frame = stream.read_frame().unwrap();
+    let Traffic::Whisper(whisper) = Traffic::decode(&frame).unwrap() else {
+        panic!("wrong variant");
+    };
+    assert_eq!((whisper.to, whisper.text), ("Brock", "Hi"));
+    assert!(frame.as_ptr_range().contains(&whisper.to.as_ptr()));
+
+    // Owned decoding still reads the following frame.
+    let ready: ClientReady = stream.read_message().unwrap();
+    assert_eq!(ready.public_key(), key);
+}
+
+#[test]
+fn generic_codec_round_trips() {
+    let batch = Batch {
+        items: vec![Whisper {
+            to: "Ash",
+            text: "Hi",
+        }],
+    };
+    let msg: Envelope<Whisper> = batch.into();
+    let bytes = msg.encode().unwrap();
+    assert_eq!(msg.encoded_len(), bytes.len());
+    assert_eq!(bytes[..2], [0x02, 1]);
+    let Envelope::Batch(decoded) = Envelope::<Whisper>::decode(&bytes).unwrap() else {
+        panic!("wrong variant");
+    };
+    assert_eq!(
+        decoded.items,
+        [Whisper {
+            to: "Ash",
+            text: "Hi"
+        }]
+    );
+    assert!(Batch::<Whisper>::try_from(Envelope::Single(Whisper { to: "", text: "" })).is_err());
+
+    let single = Envelope::Single(Whisper {
+        to: "Brock",
+        text: "",
+    });
+    let bytes = single.encode().unwrap();
+    let Envelope::Single(whisper) = Envelope::<Whisper>::decode(&bytes).unwrap() else {
+        panic!("wrong variant");
+    };
+    assert_eq!(whisper.to, "Brock");
+}
+
+mod vendor {
+    pub use pokemmo as protocol;
+}
+
+#[derive(Message, Debug, PartialEq)]
+#[message(crate = crate::vendor::protocol)]
+struct Ping {
+    nonce: u32,
+}
+
+#[pokemmo::codec::codec(crate = crate::vendor::protocol)]
+enum Heartbeat {
+    Ping(Ping) = 0x01u8,
+}
+
+#[test]
+fn crate_path_can_be_overridden() {
+    let msg = Heartbeat::Ping(Ping { nonce: 7 });
+    let bytes = msg.encode().unwrap();
+    assert_eq!(bytes, [0x01, 7, 0, 0, 0]);
+    let Heartbeat::Ping(ping) = Heartbeat::decode(&bytes).unwrap();
+    assert_eq!(ping, Ping { nonce: 7 });
+}
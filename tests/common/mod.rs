@@ -0,0 +1,18 @@
+// Each test crate uses only some of these helpers
+#![allow(dead_code)]
+
+use p256::elliptic_curve::rand_core::OsRng;
+use pokemmo::session::{Role, Session};
+
+pub const PRIMARY_OBFUSCATION_VALUE: i64 = 3214621489648854472;
+pub const SECONDARY_OBFUSCATION_VALUE: i64 = -4214651440992349575;
+
+/// Derives the client and server sessions of a fresh ECDH key pair.
+pub fn session_pair() -> (Session, Session) {
+    let client = p256::SecretKey::random(&mut OsRng);
+    let server = p256::SecretKey::random(&mut OsRng);
+    (
+        Session::derive(Role::Client, &client, &server.public_key()),
+        Session::derive(Role::Server, &server, &client.public_key()),
+    )
+}
@@ -0,0 +1,248 @@
+mod common;
+
+use common::{PRIMARY_OBFUSCATION_VALUE, SECONDARY_OBFUSCATION_VALUE};
+use p256::ecdsa::SigningKey;
+use p256::elliptic_curve::rand_core::OsRng;
+use pokemmo::message::Message;
+use pokemmo::{
+    codec::Login,
+    context::WithContext,
+    handshake::{self, ClientConfig, HandshakeError, ServerConfig},
+    identity::{ServerIdentity, TrustError, TrustStore},
+    message::{Checksum, ClientHello, ClientReady},
+    session::{Role, Session},
+};
+use std::net::{TcpListener, TcpStream};
+
+/// Runs a hand-rolled server for a single connection, returning the client's `ClientReady`.
+fn spawn_server(
+    identity: ServerIdentity,
+    checksum: Checksum,
+) -> (
+    std::net::SocketAddr,
+    std::thread::JoinHandle<Option<ClientReady>>,
+) {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+    let handle = std::thread::spawn(move || {
+        let (stream, _) = listener.accept().unwrap();
+        let mut stream = stream.with_context::<Login>();
+        stream.read_message::<ClientHello>().unwrap();
+
+        let (secret_key, server_hello) = identity.server_hello(&mut OsRng, checksum);
+        stream.write_message(server_hello).unwrap();
+
+        let client_ready = stream.read_message::<ClientReady>().ok()?;
+        let session = Session::derive(Role::Server, &secret_key, &client_ready.public_key());
+        stream.secure(&session, checksum).unwrap();
+        stream
+            .write_message(ClientReady::new(client_ready.public_key()))
+            .ok()?;
+        Some(client_ready)
+    });
+    (addr, handle)
+}
+
+#[test]
+fn client_handshake_establishes_secure_session() {
+    let identity = ServerIdentity::new(SigningKey::random(&mut OsRng));
+    let trust_store = TrustStore::new().with_key(*identity.verifying_key());
+    let (addr, server) = spawn_server(identity, Checksum::hmac(16).unwrap());
+
+    let mut config = ClientConfig::new(
+        PRIMARY_OBFUSCATION_VALUE,
+        SECONDARY_OBFUSCATION_VALUE,
+        trust_store,
+        OsRng,
+    );
+    let stream = TcpStream::connect(addr).unwrap().with_context::<Login>();
+    let mut stream = handshake::client(stream, &mut config).unwrap();
+    assert!(stream.is_secure());
+
+    // The server echoes our key back over the secure channel.
+    let echoed = stream.read_message::<ClientReady>().unwrap();
+    let client_ready = server.join().unwrap().unwrap();
+    assert_eq!(echoed.public_key(), client_ready.public_key());
+}
+
+#[test]
+fn client_handshake_rejects_untrusted_server() {
+    let identity = ServerIdentity::new(SigningKey::random(&mut OsRng));
+    let trust_store = TrustStore::new().with_key(*SigningKey::random(&mut OsRng).verifying_key());
+    let (addr, server) = spawn_server(identity, Checksum::None);
+
+    let mut config = ClientConfig::new(
+        PRIMARY_OBFUSCATION_VALUE,
+        SECONDARY_OBFUSCATION_VALUE,
+        trust_store,
+        OsRng,
+    );
+    let stream = TcpStream::connect(addr).unwrap().with_context::<Login>();
+    let Err(err) = handshake::client(stream, &mut config) else {
+        panic!("untrusted server was accepted");
+    };
+    let handshake_error = err
+        .get_ref()
+        .and_then(|e| e.downcast_ref::<HandshakeError>());
+    assert_eq!(
+        handshake_error,
+        Some(&HandshakeError::Untrusted(TrustError::UntrustedSignature))
+    );
+    assert!(server.join().unwrap().is_none());
+}
+
+fn spawn_server_driver(
+    config: ServerConfig<OsRng>,
+) -> (
+    std::net::SocketAddr,
+    std::thread::JoinHandle<std::io::Result<()>>,
+) {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+    let handle = std::thread::spawn(move || {
+        let mut config = config;
+        let (stream, _) = listener.accept().unwrap();
+        let mut stream = handshake::server(stream.with_context::<Login>(), &mut config)?;
+        let client_ready = stream.read_message::<ClientReady>()?;
+        stream.write_message(client_ready)?;
+        Ok(())
+    });
+    (addr, handle)
+}
+
+#[test]
+fn server_handshake_establishes_secure_session() {
+    let identity = ServerIdentity::new(SigningKey::random(&mut OsRng));
+    let trust_store = TrustStore::new().with_key(*identity.verifying_key());
+    let (addr, server) = spawn_server_driver(ServerConfig::new(
+        PRIMARY_OBFUSCATION_VALUE,
+        SECONDARY_OBFUSCATION_VALUE,
+        identity,
+        Checksum::Crc16,
+        OsRng,
+    ));
+
+    let mut config = ClientConfig::new(
+        PRIMARY_OBFUSCATION_VALUE,
+        SECONDARY_OBFUSCATION_VALUE,
+        trust_store,
+        OsRng,
+    );
+    let stream = TcpStream::connect(addr).unwrap().with_context::<Login>();
+    let mut stream = handshake::client(stream, &mut config).unwrap();
+
+    let key = p256::SecretKey::random(&mut OsRng).public_key();
+    stream.write_message(ClientReady::new(key)).unwrap();
+    let echoed = stream.read_message::<ClientReady>().unwrap();
+    assert_eq!(echoed.public_key(), key);
+    server.join().unwrap().unwrap();
+}
+
+#[test]
+fn server_handshake_rejects_stale_timestamp() {
+    let identity = ServerIdentity::new(SigningKey::random(&mut OsRng));
+    let (addr, server) = spawn_server_driver(
+        ServerConfig::new(
+            PRIMARY_OBFUSCATION_VALUE,
+            SECONDARY_OBFUSCATION_VALUE,
+            identity,
+            Checksum::None,
+            OsRng,
+        )
+        .with_max_clock_skew(std::time::Duration::from_secs(5)),
+    );
+
+    let mut stream = TcpStream::connect(addr).unwrap().with_context::<Login>();
+    let client_hello = ClientHello::new(
+        42,
+        std::time::SystemTime::now() - std::time::Duration::from_secs(60),
+        PRIMARY_OBFUSCATION_VALUE,
+        SECONDARY_OBFUSCATION_VALUE,
+    )
+    .unwrap();
+    stream.write_message(client_hello).unwrap();
+
+    let err = server.join().unwrap().unwrap_err();
+    let handshake_error = err
+        .get_ref()
+        .and_then(|e| e.downcast_ref::<HandshakeError>());
+    assert_eq!(handshake_error, Some(&HandshakeError::TimestampOutOfWindow));
+}
+
+/// Returns a serialized `ClientReady` whose key is not on the curve.
+fn malformed_client_ready() -> Vec<u8> {
+    let key = p256::SecretKey::random(&mut OsRng).public_key();
+    let mut bytes = ClientReady::new(key).serialize().unwrap();
+    bytes[3] ^= 0xFF;
+    bytes
+}
+
+#[test]
+fn client_handshake_rejects_malformed_server_key() {
+    let identity = ServerIdentity::new(SigningKey::random(&mut OsRng));
+    let trust_store = TrustStore::new().with_key(*identity.verifying_key());
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+    let server = std::thread::spawn(move || {
+        let (stream, _) = listener.accept().unwrap();
+        let mut stream = stream.with_context::<Login>();
+        stream.read_message::<ClientHello>().unwrap();
+        let (_, server_hello) = identity.server_hello(&mut OsRng, Checksum::None);
+        let mut data = server_hello.serialize().unwrap();
+        data[3] ^= 0xFF;
+        stream
+            .write_message(Login::Unknown { opcode: 0x01, data })
+            .unwrap();
+    });
+
+    let mut config = ClientConfig::new(
+        PRIMARY_OBFUSCATION_VALUE,
+        SECONDARY_OBFUSCATION_VALUE,
+        trust_store,
+        OsRng,
+    );
+    let stream = TcpStream::connect(addr).unwrap().with_context::<Login>();
+    let Err(err) = handshake::client(stream, &mut config) else {
+        panic!("malformed server key was accepted");
+    };
+    let handshake_error = err
+        .get_ref()
+        .and_then(|e| e.downcast_ref::<HandshakeError>());
+    assert_eq!(handshake_error, Some(&HandshakeError::MalformedPublicKey));
+    server.join().unwrap();
+}
+
+#[test]
+fn server_handshake_rejects_malformed_client_key() {
+    let identity = ServerIdentity::new(SigningKey::random(&mut OsRng));
+    let (addr, server) = spawn_server_driver(ServerConfig::new(
+        PRIMARY_OBFUSCATION_VALUE,
+        SECONDARY_OBFUSCATION_VALUE,
+        identity,
+        Checksum::None,
+        OsRng,
+    ));
+
+    let mut stream = TcpStream::connect(addr).unwrap().with_context::<Login>();
+    let client_hello = ClientHello::new(
+        42,
+        std::time::SystemTime::now(),
+        PRIMARY_OBFUSCATION_VALUE,
+        SECONDARY_OBFUSCATION_VALUE,
+    )
+    .unwrap();
+    stream.write_message(client_hello).unwrap();
+    stream.read_message::<Login>().unwrap();
+    stream
+        .write_message(Login::Unknown {
+            opcode: 0x02,
+            data: malformed_client_ready(),
+        })
+        .unwrap();
+
+    let err = server.join().unwrap().unwrap_err();
+    let handshake_error = err
+        .get_ref()
+        .and_then(|e| e.downcast_ref::<HandshakeError>());
+    assert_eq!(handshake_error, Some(&HandshakeError::MalformedPublicKey));
+}
@@ -0,0 +1,148 @@
+#![allow(dead_code, non_camel_case_types, unused_macros)]
+
+/// Shadows the std paths and prelude names the derives could otherwise pick up, so the
+/// expansion only compiles if it uses fully qualified paths.
+mod shadowed {
+    use pokemmo::message::Message;
+
+    mod std {}
+    mod core {}
+    struct Result;
+    struct Option;
+    struct Vec;
+    struct String;
+    struct Box;
+    struct Ok;
+    struct Err;
+    struct Some;
+    struct None;
+    struct TryFrom;
+    struct TryInto;
+    struct Into;
+
+    macro_rules! concat {
+        () => {};
+    }
+    macro_rules! stringify {
+        () => {};
+    }
+    macro_rules! vec {
+        () => {};
+    }
+    macro_rules! unreachable {
+        () => {};
+    }
+
+    #[derive(Message, Debug, PartialEq)]
+    #[strict]
+    struct Everything {
+        flags: u8,
+        #[varint]
+        varint: u32,
+        #[zigzag]
+        zigzag: i16,
+        pair: [u16; 2],
+        #[varint]
+        bytes: [u8; 2],
+        enabled: bool,
+        #[prefixed(u8)]
+        utf8: ::std::string::String,
+        #[prefixed(u8, utf8, chars)]
+        utf8_chars: ::std::string::String,
+        #[prefixed(u16, utf16)]
+        utf16: ::std::string::String,
+        #[prefixed(u16, utf16, bytes)]
+        utf16_bytes: ::std::string::String,
+        #[prefixed(u8)]
+        blob: ::std::vec::Vec<u8>,
+        #[count(u8)]
+        numbers: ::std::vec::Vec<u16>,
+        #[count(u8)]
+        results: ::std::vec::Vec<Outcome>,
+        #[optional(u8)]
+        level: ::core::option::Option<u16>,
+        #[present_if(flags & 0x1 != 0)]
+        bonus: ::core::option::Option<u8>,
+        #[with(pokemmo::message::sec1_i16)]
+        key: p256::PublicKey,
+        #[remainder]
+        rest: ::std::vec::Vec<u8>,
+    }
+
+    #[derive(Message, Debug, PartialEq)]
+    #[repr(u8)]
+    #[tag(u8)]
+    enum Outcome {
+        Won { turns: u16 } = 1,
+        Lost(#[prefixed(u8)] ::std::string::String) = 2,
+        #[fallback]
+        Other { tag: u8, data: ::std::vec::Vec<u8> },
+    }
+
+    #[derive(Message, Debug, PartialEq)]
+    struct Borrowed<'a> {
+        #[prefixed(u8)]
+        name: &'a str,
+        #[prefixed(u8)]
+        data: &'a [u8],
+    }
+
+    #[pokemmo::codec::codec]
+    enum Traffic<'a> {
+        Outcome(Outcome) = 0x01u8,
+        Borrowed(Borrowed<'a>) = 0x02u8,
+        Unknown { opcode: u8, data: &'a [u8] },
+    }
+
+    #[test]
+    fn messages_round_trip() {
+        use p256::elliptic_curve::rand_core::OsRng;
+
+        let msg = Everything {
+            flags: 1,
+            varint: 300,
+            zigzag: -2,
+            pair: [1, 2],
+            bytes: [200, 1],
+            enabled: true,
+            utf8: "Ash".to_owned(),
+            utf8_chars: "Pokémon".to_owned(),
+            utf16: "Misty".to_owned(),
+            utf16_bytes: "Brock".to_owned(),
+            blob: ::std::vec![1, 2],
+            numbers: ::std::vec![3, 4],
+            results: ::std::vec![
+                Outcome::Won { turns: 5 },
+                Outcome::Lost("Gary".to_owned()),
+            ],
+            level: ::core::option::Option::Some(5),
+            bonus: ::core::option::Option::Some(6),
+            key: p256::SecretKey::random(&mut OsRng).public_key(),
+            rest: ::std::vec![7],
+        };
+        let bytes = msg.serialize().unwrap();
+        assert_eq!(msg.encoded_len(), bytes.len());
+        assert_eq!(Everything::deserialize(&bytes).unwrap(), msg);
+    }
+
+    #[test]
+    fn codec_round_trips() {
+        use pokemmo::codec::Codec;
+
+        let msg = Traffic::Borrowed(Borrowed {
+            name: "Ash",
+            data: &[1, 2],
+        });
+        let bytes = msg.encode().unwrap();
+        assert_eq!(msg.encoded_len(), bytes.len());
+        let ::core::result::Result::Ok(Borrowed { name, data }) =
+            Borrowed::try_from(Traffic::decode(&bytes).unwrap())
+        else {
+            panic!("wrong variant");
+        };
+        assert_eq!((name, data), ("Ash", &[1u8, 2][..]));
+
+        let outcome: Traffic = Outcome::Won { turns: 1 }.into();
+        assert_eq!(outcome.encode().unwrap(), [0x01, 1, 1, 0]);
+    }
+}
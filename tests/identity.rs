@@ -0,0 +1,85 @@
+use p256::ecdsa::{signature::Signer, SigningKey};
+use p256::elliptic_curve::rand_core::OsRng;
+use pokemmo::{
+    identity::{ServerIdentity, TrustError, TrustStore},
+    message::{Checksum, ServerHello},
+};
+
+fn signed_hello(identity: &SigningKey) -> (p256::PublicKey, ServerHello) {
+    let ephemeral = p256::SecretKey::random(&mut OsRng).public_key();
+    let signature = identity.sign(&ephemeral.to_sec1_bytes());
+    (
+        ephemeral,
+        ServerHello::new(ephemeral, signature, Checksum::None),
+    )
+}
+
+#[test]
+fn trust_store_accepts_pinned_key() {
+    let identity = SigningKey::random(&mut OsRng);
+    let trust_store = TrustStore::new().with_key(*identity.verifying_key());
+    let (ephemeral, server_hello) = signed_hello(&identity);
+
+    assert_eq!(trust_store.verify(&server_hello), Ok(ephemeral));
+}
+
+#[test]
+fn trust_store_rejects_unpinned_key() {
+    let pinned = SigningKey::random(&mut OsRng);
+    let trust_store = TrustStore::new().with_key(*pinned.verifying_key());
+    let (_, server_hello) = signed_hello(&SigningKey::random(&mut OsRng));
+
+    assert_eq!(
+        trust_store.verify(&server_hello),
+        Err(TrustError::UntrustedSignature)
+    );
+    assert_eq!(
+        TrustStore::new().verify(&server_hello),
+        Err(TrustError::UntrustedSignature)
+    );
+}
+
+#[test]
+fn trust_store_supports_rotation() {
+    let old = SigningKey::random(&mut OsRng);
+    let new = SigningKey::random(&mut OsRng);
+    let mut trust_store = TrustStore::new().with_key(*old.verifying_key());
+    trust_store.add(*new.verifying_key());
+
+    let (_, old_hello) = signed_hello(&old);
+    let (_, new_hello) = signed_hello(&new);
+    assert!(trust_store.verify(&old_hello).is_ok());
+    assert!(trust_store.verify(&new_hello).is_ok());
+
+    assert!(trust_store.remove(old.verifying_key()));
+    assert!(!trust_store.remove(old.verifying_key()));
+    assert!(trust_store.verify(&old_hello).is_err());
+    assert!(trust_store.verify(&new_hello).is_ok());
+}
+
+#[test]
+fn server_identity_signs_fresh_ephemeral_keys() {
+    let identity = ServerIdentity::new(SigningKey::random(&mut OsRng));
+    let trust_store = TrustStore::new().with_key(*identity.verifying_key());
+
+    let (first_secret_key, first_hello) = identity.server_hello(&mut OsRng, Checksum::Crc16);
+    let (second_secret_key, second_hello) = identity.server_hello(&mut OsRng, Checksum::Crc16);
+
+    assert_eq!(
+        trust_store.verify(&first_hello),
+        Ok(first_secret_key.public_key())
+    );
+    assert_eq!(
+        trust_store.verify(&second_hello),
+        Ok(second_secret_key.public_key())
+    );
+    assert_ne!(
+        first_secret_key.public_key(),
+        second_secret_key.public_key()
+    );
+    assert_ne!(
+        p256::PublicKey::from(identity.verifying_key()),
+        first_secret_key.public_key()
+    );
+    assert_eq!(first_hello.checksum(), Ok(Checksum::Crc16));
+}
@@ -0,0 +1,304 @@
+mod common;
+
+use common::session_pair;
+use p256::elliptic_curve::rand_core::OsRng;
+use pokemmo::{
+    codec::Login,
+    context::{FrameError, WithContext},
+    integrity::{crc16, hmac_sha256, verify_hmac_sha256, Integrity},
+    message::{Checksum, ClientReady},
+    session::Session,
+};
+
+#[test]
+fn crc16_known_answers() {
+    assert_eq!(crc16(b""), 0xFFFF);
+    assert_eq!(crc16(b"A"), 0xB915);
+    assert_eq!(crc16(b"123456789"), 0x29B1);
+    assert_eq!(crc16(&[0u8; 4]), 0x84C0);
+}
+
+#[test]
+fn hmac_sha256_known_answers() {
+    // RFC 4231, test case 2.
+    let key = b"Jefe";
+    let data = b"what do ya want for nothing?";
+    let tag = [
+        0x5b, 0xdc, 0xc1, 0x46, 0xbf, 0x60, 0x75, 0x4e, 0x6a, 0x04, 0x24, 0x26, 0x08, 0x95, 0x75,
+        0xc7, 0x5a, 0x00, 0x3f, 0x08, 0x9d, 0x27, 0x39, 0x83, 0x9d, 0xec, 0x58, 0xb9, 0x64, 0xec,
+        0x38, 0x43,
+    ];
+
+    assert_eq!(hmac_sha256(key, data, 32), tag);
+    assert_eq!(hmac_sha256(key, data, 16), tag[..16]);
+    assert!(verify_hmac_sha256(key, data, &tag[..4]));
+    assert!(!verify_hmac_sha256(
+        key,
+        b"what do ya want for nothing!",
+        &tag[..16]
+    ));
+    assert!(!verify_hmac_sha256(key, data, &[]));
+}
+
+fn write_frames(session: &Session, checksum: Checksum, count: usize) -> Vec<u8> {
+    let mut stream = std::io::Cursor::new(Vec::new()).with_context::<Login>();
+    stream.secure(session, checksum).unwrap();
+    for _ in 0..count {
+        let key = p256::SecretKey::random(&mut OsRng).public_key();
+        stream.write_message(ClientReady::new(key)).unwrap();
+    }
+    stream.get_ref().clone()
+}
+
+#[test]
+fn crc16_frames_round_trip() {
+    let (client, server) = session_pair();
+    let frames = write_frames(&client, Checksum::Crc16, 2);
+
+    let mut stream = std::io::Cursor::new(frames).with_context::<Login>();
+    stream.secure(&server, Checksum::Crc16).unwrap();
+    stream.read_message::<ClientReady>().unwrap();
+    stream.read_message::<ClientReady>().unwrap();
+}
+
+#[test]
+fn crc16_rejects_corrupted_frame() {
+    let (client, server) = session_pair();
+    let mut frames = write_frames(&client, Checksum::Crc16, 1);
+    frames[4] ^= 0x01;
+
+    let mut stream = std::io::Cursor::new(frames).with_context::<Login>();
+    stream.secure(&server, Checksum::Crc16).unwrap();
+    let Err(err) = stream.read_message::<ClientReady>() else {
+        panic!("corrupted frame was accepted");
+    };
+    let frame_error = err.get_ref().and_then(|e| e.downcast_ref::<FrameError>());
+    assert_eq!(frame_error, Some(&FrameError::ChecksumMismatch));
+}
+
+#[test]
+fn hmac_sha256_frames_round_trip() {
+    let (client, server) = session_pair();
+    let frames = write_frames(&client, Checksum::hmac(16).unwrap(), 2);
+
+    let mut stream = std::io::Cursor::new(frames).with_context::<Login>();
+    stream.secure(&server, Checksum::hmac(16).unwrap()).unwrap();
+    stream.read_message::<ClientReady>().unwrap();
+    stream.read_message::<ClientReady>().unwrap();
+}
+
+#[test]
+fn hmac_sha256_rejects_corrupted_tag() {
+    let (client, server) = session_pair();
+    let mut frames = write_frames(&client, Checksum::hmac(8).unwrap(), 1);
+    let last = frames.len() - 1;
+    frames[last] ^= 0x80;
+
+    let mut stream = std::io::Cursor::new(frames).with_context::<Login>();
+    stream.secure(&server, Checksum::hmac(8).unwrap()).unwrap();
+    let Err(err) = stream.read_message::<ClientReady>() else {
+        panic!("corrupted frame was accepted");
+    };
+    let frame_error = err.get_ref().and_then(|e| e.downcast_ref::<FrameError>());
+    assert_eq!(frame_error, Some(&FrameError::ChecksumMismatch));
+}
+
+#[test]
+fn checksum_size_mapping_matches_spec() {
+    let valid = [
+        (0, Checksum::None),
+        (2, Checksum::Crc16),
+        (4, Checksum::hmac(4).unwrap()),
+        (16, Checksum::hmac(16).unwrap()),
+        (32, Checksum::hmac(32).unwrap()),
+    ];
+    for (size, checksum) in valid {
+        assert_eq!(Checksum::try_from(size), Ok(checksum));
+        assert_eq!(i8::from(checksum), size);
+        let integrity = checksum.integrity(&[0u8; 32]).unwrap();
+        assert_eq!(integrity.tag_len(), size as usize);
+        assert_eq!(integrity.compute(b"packet").len(), size as usize);
+    }
+
+    for size in [-1, 1, 3, 33, i8::MAX, i8::MIN] {
+        assert!(Checksum::try_from(size).is_err());
+    }
+    for size in [-1, 0, 2, 3, 33, i8::MAX] {
+        assert_eq!(Checksum::hmac(size), None);
+    }
+}
+
+#[test]
+fn every_checksum_size_round_trips() {
+    for size in i8::MIN..=i8::MAX {
+        let Ok(checksum) = Checksum::try_from(size) else {
+            continue;
+        };
+        assert_eq!(i8::from(checksum), size);
+        if (4..=32).contains(&size) {
+            assert_eq!(Checksum::hmac(size), Some(checksum));
+        }
+    }
+}
+
+struct Xor8;
+
+impl Integrity for Xor8 {
+    fn tag_len(&self) -> usize {
+        1
+    }
+
+    fn compute(&self, data: &[u8]) -> Vec<u8> {
+        vec![data.iter().fold(0, |acc, byte| acc ^ byte)]
+    }
+}
+
+#[test]
+fn custom_integrity_frames_round_trip() {
+    let (client, server) = session_pair();
+    let mut stream = std::io::Cursor::new(Vec::new()).with_context::<Login>();
+    stream.secure_with(&client, Box::new(Xor8), Box::new(Xor8));
+    let key = p256::SecretKey::random(&mut OsRng).public_key();
+    stream.write_message(ClientReady::new(key)).unwrap();
+    let mut frames = stream.get_ref().clone();
+
+    let mut stream = std::io::Cursor::new(frames.clone()).with_context::<Login>();
+    stream.secure_with(&server, Box::new(Xor8), Box::new(Xor8));
+    let ready = stream.read_message::<ClientReady>().unwrap();
+    assert_eq!(ready.public_key(), key);
+
+    let last = frames.len() - 1;
+    frames[last] ^= 0x01;
+    let mut stream = std::io::Cursor::new(frames).with_context::<Login>();
+    stream.secure_with(&server, Box::new(Xor8), Box::new(Xor8));
+    assert!(stream.read_message::<ClientReady>().is_err());
+}
+
+#[test]
+fn default_verify_checks_value_and_length() {
+    let tag = Xor8.compute(b"packet");
+    assert!(Xor8.verify(b"packet", &tag));
+    assert!(!Xor8.verify(b"packet", &[tag[0] ^ 0x01]));
+    assert!(!Xor8.verify(b"packet", &[]));
+    assert!(!Xor8.verify(b"packet", &[tag[0], 0]));
+}
+
+fn split_frames(mut bytes: &[u8]) -> Vec<Vec<u8>> {
+    let mut frames = Vec::new();
+    while !bytes.is_empty() {
+        let length = i16::from_le_bytes([bytes[0], bytes[1]]) as usize;
+        frames.push(bytes[..length].to_vec());
+        bytes = &bytes[length..];
+    }
+    frames
+}
+
+fn read_error(session: &Session, checksum: Checksum, frames: Vec<u8>, reads: usize) -> FrameError {
+    let mut stream = std::io::Cursor::new(frames).with_context::<Login>();
+    stream.secure(session, checksum).unwrap();
+    for _ in 0..reads {
+        if let Err(err) = stream.read_message::<ClientReady>() {
+            return err
+                .get_ref()
+                .and_then(|e| e.downcast_ref::<FrameError>())
+                .cloned()
+                .expect("not a frame error");
+        }
+    }
+    panic!("all frames were accepted");
+}
+
+#[test]
+fn replayed_frame_is_detected() {
+    let (client, server) = session_pair();
+    let frames = split_frames(&write_frames(&client, Checksum::Crc16, 3));
+    let replayed = [&frames[0][..], &frames[0], &frames[1], &frames[2]].concat();
+
+    assert_eq!(
+        read_error(&server, Checksum::Crc16, replayed, 4),
+        FrameError::ChecksumMismatch
+    );
+}
+
+#[test]
+fn reordered_frames_are_detected() {
+    let (client, server) = session_pair();
+    let frames = split_frames(&write_frames(&client, Checksum::hmac(16).unwrap(), 3));
+    let reordered = [&frames[0][..], &frames[2], &frames[1]].concat();
+
+    assert_eq!(
+        read_error(&server, Checksum::hmac(16).unwrap(), reordered, 3),
+        FrameError::ChecksumMismatch
+    );
+}
+
+#[test]
+fn desync_rejects_later_frames() {
+    let (client, server) = session_pair();
+    let frames = split_frames(&write_frames(&client, Checksum::Crc16, 3));
+    let dropped = [&frames[0][..], &frames[2], &frames[1], &frames[2]].concat();
+
+    let mut stream = std::io::Cursor::new(dropped).with_context::<Login>();
+    stream.secure(&server, Checksum::Crc16).unwrap();
+    stream.read_message::<ClientReady>().unwrap();
+    let Err(err) = stream.read_message::<ClientReady>() else {
+        panic!("frame after a dropped one was accepted");
+    };
+    let frame_error = err.get_ref().and_then(|e| e.downcast_ref::<FrameError>());
+    assert_eq!(frame_error, Some(&FrameError::ChecksumMismatch));
+    for _ in 0..2 {
+        let Err(err) = stream.read_message::<ClientReady>() else {
+            panic!("frame accepted after desync");
+        };
+        let frame_error = err.get_ref().and_then(|e| e.downcast_ref::<FrameError>());
+        assert_eq!(frame_error, Some(&FrameError::Desync { sequence: 1 }));
+    }
+}
+
+/// A stream whose writes fail while `broken` is set.
+struct FlakyStream {
+    broken: bool,
+    written: Vec<u8>,
+}
+
+impl std::io::Read for FlakyStream {
+    fn read(&mut self, _: &mut [u8]) -> std::io::Result<usize> {
+        Ok(0)
+    }
+}
+
+impl std::io::Write for FlakyStream {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        if self.broken {
+            return Err(std::io::ErrorKind::BrokenPipe.into());
+        }
+        self.written.extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+#[test]
+fn failed_write_desyncs_outbound_direction() {
+    let (client, _) = session_pair();
+    let stream = FlakyStream {
+        broken: false,
+        written: Vec::new(),
+    };
+    let mut stream = stream.with_context::<Login>();
+    stream.secure(&client, Checksum::Crc16).unwrap();
+    let key = p256::SecretKey::random(&mut OsRng).public_key();
+    stream.write_message(ClientReady::new(key)).unwrap();
+
+    stream.broken = true;
+    let err = stream.write_message(ClientReady::new(key)).unwrap_err();
+    assert_eq!(err.kind(), std::io::ErrorKind::BrokenPipe);
+
+    stream.broken = false;
+    let err = stream.write_message(ClientReady::new(key)).unwrap_err();
+    let frame_error = err.get_ref().and_then(|e| e.downcast_ref::<FrameError>());
+    assert_eq!(frame_error, Some(&FrameError::Desync { sequence: 1 }));
+}
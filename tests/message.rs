@@ -0,0 +1,842 @@
+use p256::elliptic_curve::rand_core::OsRng;
+use pokemmo::message::{sec1_i16::MalformedPublicKey, ClientReady, Field, Message, MessageOwned};
+
+#[derive(Message, Debug, PartialEq)]
+struct Strings {
+    #[prefixed(i16)]
+    utf8: String,
+    #[prefixed(u8, utf8, chars)]
+    utf8_chars: String,
+    #[prefixed(i16, utf16)]
+    utf16: String,
+    #[prefixed(i32, utf16, bytes)]
+    utf16_bytes: String,
+}
+
+#[test]
+fn strings_round_trip() {
+    let msg = Strings {
+        utf8: "Pokémon".to_string(),
+        utf8_chars: "Pokémon 🎮".to_string(),
+        utf16: "Pokémon 🎮".to_string(),
+        utf16_bytes: "Ash".to_string(),
+    };
+    let bytes = msg.serialize().unwrap();
+    assert_eq!(Strings::deserialize(&bytes).unwrap(), msg);
+}
+
+#[test]
+fn string_prefixes_count_declared_unit() {
+    let msg = Strings {
+        utf8: "é".to_string(),
+        utf8_chars: "é🎮".to_string(),
+        utf16: "🎮".to_string(),
+        utf16_bytes: "A".to_string(),
+    };
+    let bytes = msg.serialize().unwrap();
+    assert_eq!(
+        bytes,
+        [
+            &[2, 0][..],
+            &[0xC3, 0xA9],
+            &[2, 0xC3, 0xA9, 0xF0, 0x9F, 0x8E, 0xAE],
+            &[2, 0, 0x3C, 0xD8, 0xAE, 0xDF],
+            &[2, 0, 0, 0, 0x41, 0],
+        ]
+        .concat()
+    );
+}
+
+#[test]
+fn strings_reject_invalid_data() {
+    // Invalid UTF-8 continuation byte.
+    assert!(Strings::deserialize(&[1, 0, 0xC3]).is_err());
+    // Unpaired UTF-16 surrogate.
+    assert!(Strings::deserialize(&[0, 0, 0, 1, 0, 0x3C, 0xD8, 0, 0, 0, 0]).is_err());
+    // Odd UTF-16 byte length.
+    assert!(Strings::deserialize(&[0, 0, 0, 0, 0, 1, 0, 0, 0, 0x41]).is_err());
+}
+
+#[derive(Message, Debug, PartialEq)]
+struct Position {
+    x: i32,
+    y: i32,
+}
+
+#[derive(Message, Debug, PartialEq)]
+struct Warp {
+    map: u16,
+    from: Position,
+    to: Position,
+    #[prefixed(u8)]
+    name: String,
+    flags: u8,
+}
+
+#[test]
+fn nested_messages_are_encoded_inline() {
+    let msg = Warp {
+        map: 7,
+        from: Position { x: 1, y: -1 },
+        to: Position { x: 256, y: 3 },
+        name: "Cave".to_string(),
+        flags: 0x80,
+    };
+    let bytes = msg.serialize().unwrap();
+    assert_eq!(
+        bytes,
+        [
+            &[7, 0][..],
+            &[1, 0, 0, 0, 0xFF, 0xFF, 0xFF, 0xFF],
+            &[0, 1, 0, 0, 3, 0, 0, 0],
+            &[4, b'C', b'a', b'v', b'e'],
+            &[0x80],
+        ]
+        .concat()
+    );
+    assert_eq!(Warp::deserialize(&bytes).unwrap(), msg);
+    assert!(Warp::deserialize(&bytes[..10]).is_err());
+}
+
+#[derive(Message, Debug, PartialEq)]
+struct Route {
+    #[count(i16)]
+    waypoints: Vec<Position>,
+    #[count(u8)]
+    warps: Vec<Warp>,
+}
+
+#[test]
+fn counted_vecs_round_trip() {
+    let msg = Route {
+        waypoints: vec![Position { x: 1, y: 2 }, Position { x: 3, y: 4 }],
+        warps: vec![Warp {
+            map: 1,
+            from: Position { x: 0, y: 0 },
+            to: Position { x: 5, y: 5 },
+            name: String::new(),
+            flags: 0,
+        }],
+    };
+    let bytes = msg.serialize().unwrap();
+    assert_eq!(bytes[..2], [2, 0]);
+    assert_eq!(bytes[18], 1);
+    assert_eq!(Route::deserialize(&bytes).unwrap(), msg);
+}
+
+#[test]
+fn counted_vecs_reject_bad_counts() {
+    // Negative count.
+    assert!(Route::deserialize(&[0xFF, 0xFF, 0]).is_err());
+    // Count larger than the remaining data.
+    assert!(Route::deserialize(&[0x00, 0x10, 0]).is_err());
+}
+
+#[derive(Message, Debug, PartialEq)]
+struct Primitives {
+    visible: bool,
+    x: f32,
+    y: f64,
+    hash: [u8; 4],
+    stats: [u16; 3],
+    corners: [Position; 2],
+}
+
+#[test]
+fn bool_float_and_array_fields_round_trip() {
+    let msg = Primitives {
+        visible: true,
+        x: 1.5,
+        y: -0.25,
+        hash: [0xDE, 0xAD, 0xBE, 0xEF],
+        stats: [1, 2, 0x0300],
+        corners: [Position { x: 1, y: 2 }, Position { x: 3, y: 4 }],
+    };
+    let bytes = msg.serialize().unwrap();
+    assert_eq!(bytes[0], 1);
+    assert_eq!(bytes[1..5], 1.5f32.to_le_bytes());
+    assert_eq!(bytes[5..13], (-0.25f64).to_le_bytes());
+    assert_eq!(bytes[13..17], [0xDE, 0xAD, 0xBE, 0xEF]);
+    assert_eq!(bytes[17..23], [1, 0, 2, 0, 0, 3]);
+    assert_eq!(bytes.len(), 23 + 16);
+    assert_eq!(Primitives::deserialize(&bytes).unwrap(), msg);
+}
+
+#[test]
+fn bool_fields_are_strict() {
+    let msg = Primitives {
+        visible: false,
+        x: 0.0,
+        y: 0.0,
+        hash: [0; 4],
+        stats: [0; 3],
+        corners: [Position { x: 0, y: 0 }, Position { x: 0, y: 0 }],
+    };
+    let mut bytes = msg.serialize().unwrap();
+    assert!(Primitives::deserialize(&bytes).is_ok());
+    bytes[0] = 2;
+    assert!(Primitives::deserialize(&bytes).is_err());
+}
+
+#[derive(Message, Debug, PartialEq)]
+struct Spawn {
+    flags: u8,
+    #[present_if(flags & 0x1 != 0)]
+    position: Option<Position>,
+    #[optional(u8)]
+    #[prefixed(i16, utf16)]
+    nickname: Option<String>,
+    #[optional(bool)]
+    level: Option<u16>,
+}
+
+#[test]
+fn optional_fields_round_trip() {
+    let msg = Spawn {
+        flags: 0x1,
+        position: Some(Position { x: 1, y: 2 }),
+        nickname: Some("Pika".to_string()),
+        level: None,
+    };
+    let bytes = msg.serialize().unwrap();
+    assert_eq!(bytes[9], 1);
+    assert_eq!(bytes[10..12], [4, 0]);
+    assert_eq!(bytes[20..], [0]);
+    assert_eq!(Spawn::deserialize(&bytes).unwrap(), msg);
+
+    let msg = Spawn {
+        flags: 0x2,
+        position: None,
+        nickname: None,
+        level: Some(5),
+    };
+    let bytes = msg.serialize().unwrap();
+    assert_eq!(bytes, [0x2, 0, 1, 5, 0]);
+    assert_eq!(Spawn::deserialize(&bytes).unwrap(), msg);
+}
+
+#[test]
+fn optional_fields_are_checked() {
+    // Presence disagrees with the condition.
+    let msg = Spawn {
+        flags: 0x0,
+        position: Some(Position { x: 1, y: 2 }),
+        nickname: None,
+        level: None,
+    };
+    assert!(msg.serialize().is_err());
+    let msg = Spawn {
+        flags: 0x1,
+        position: None,
+        nickname: None,
+        level: None,
+    };
+    assert!(msg.serialize().is_err());
+
+    // Presence flags other than 0 or 1.
+    assert!(Spawn::deserialize(&[0x0, 2, 0]).is_err());
+    assert!(Spawn::deserialize(&[0x0, 0, 2]).is_err());
+}
+
+#[derive(Message, Debug, PartialEq)]
+#[repr(u8)]
+#[tag(u8)]
+enum LoginResult {
+    Success { session: u64 } = 0x01,
+    Banned(#[prefixed(i16, utf16)] String, u32) = 0x02,
+    Maintenance = 0x03,
+    #[fallback]
+    Other { tag: u8, data: Vec<u8> },
+}
+
+#[test]
+fn tagged_enums_round_trip() {
+    let results = [
+        LoginResult::Success { session: 7 },
+        LoginResult::Banned("Cheating".to_string(), 3600),
+        LoginResult::Maintenance,
+    ];
+    for result in results {
+        let bytes = result.serialize().unwrap();
+        assert_eq!(LoginResult::deserialize(&bytes).unwrap(), result);
+    }
+
+    let bytes = LoginResult::Banned("ab".to_string(), 1).serialize().unwrap();
+    assert_eq!(bytes, [0x02, 2, 0, b'a', 0, b'b', 0, 1, 0, 0, 0]);
+    assert_eq!(LoginResult::Maintenance.serialize().unwrap(), [0x03]);
+}
+
+#[test]
+fn tagged_enums_fall_back_on_unknown_tags() {
+    let other = LoginResult::deserialize(&[0x7F, 1, 2, 3]).unwrap();
+    assert_eq!(
+        other,
+        LoginResult::Other {
+            tag: 0x7F,
+            data: vec![1, 2, 3]
+        }
+    );
+    assert_eq!(other.serialize().unwrap(), [0x7F, 1, 2, 3]);
+}
+
+#[derive(Message, Debug, PartialEq)]
+#[tag(i16)]
+enum EntityUpdate {
+    Moved = 1,
+    Removed = -1,
+}
+
+#[derive(Message, Debug, PartialEq)]
+struct Entity {
+    id: u32,
+    update: EntityUpdate,
+}
+
+#[test]
+fn tagged_enums_nest_in_messages() {
+    let msg = Entity {
+        id: 9,
+        update: EntityUpdate::Removed,
+    };
+    let bytes = msg.serialize().unwrap();
+    assert_eq!(bytes, [9, 0, 0, 0, 0xFF, 0xFF]);
+    assert_eq!(Entity::deserialize(&bytes).unwrap(), msg);
+
+    // Unknown tag without a fallback variant.
+    assert!(Entity::deserialize(&[9, 0, 0, 0, 2, 0]).is_err());
+}
+
+#[derive(Message, Debug, PartialEq)]
+struct Formats {
+    #[be]
+    id: u32,
+    #[be]
+    speed: f32,
+    #[varint]
+    experience: u64,
+    #[varint]
+    small: i8,
+    #[zigzag]
+    delta: i32,
+    #[be]
+    stats: [u16; 2],
+    #[varint]
+    #[optional(u8)]
+    money: Option<u32>,
+    #[prefixed(u32, varint)]
+    name: String,
+    #[count(u16, be)]
+    path: Vec<Position>,
+}
+
+#[test]
+fn number_formats_round_trip() {
+    let msg = Formats {
+        id: 0x01020304,
+        speed: 1.5,
+        experience: 300,
+        small: -1,
+        delta: -2,
+        stats: [1, 2],
+        money: Some(127),
+        name: "Ash".to_string(),
+        path: vec![Position { x: 1, y: 2 }],
+    };
+    let bytes = msg.serialize().unwrap();
+    assert_eq!(bytes[..4], [1, 2, 3, 4]);
+    assert_eq!(bytes[4..8], 1.5f32.to_be_bytes());
+    assert_eq!(bytes[8..10], [0xAC, 0x02]);
+    assert_eq!(bytes[10..12], [0xFF, 0x01]);
+    assert_eq!(bytes[12..13], [0x03]);
+    assert_eq!(bytes[13..17], [0, 1, 0, 2]);
+    assert_eq!(bytes[17..19], [1, 0x7F]);
+    assert_eq!(bytes[19..23], [3, b'A', b's', b'h']);
+    assert_eq!(bytes[23..25], [0, 1]);
+    assert_eq!(Formats::deserialize(&bytes).unwrap(), msg);
+}
+
+#[derive(Message, Debug, PartialEq)]
+struct Zigzags {
+    #[zigzag]
+    min: i64,
+    #[varint]
+    #[zigzag]
+    max: i16,
+}
+
+#[test]
+fn zigzag_and_varint_limits() {
+    let msg = Zigzags {
+        min: i64::MIN,
+        max: i16::MAX,
+    };
+    let bytes = msg.serialize().unwrap();
+    assert_eq!(bytes.len(), 10 + 3);
+    assert_eq!(bytes[10..], [0xFE, 0xFF, 0x03]);
+    assert_eq!(Zigzags::deserialize(&bytes).unwrap(), msg);
+
+    // Varints that overflow their type.
+    let mut overflow = bytes[..10].to_vec();
+    overflow.extend_from_slice(&[0xFE, 0xFF, 0x04]);
+    assert!(Zigzags::deserialize(&overflow).is_err());
+    let mut overflow = vec![0xFF; 10];
+    overflow.extend_from_slice(&[0x01, 0x00]);
+    assert!(Zigzags::deserialize(&overflow).is_err());
+    // Truncated varint.
+    assert!(Zigzags::deserialize(&[0x80]).is_err());
+}
+
+#[derive(Message, Debug, PartialEq)]
+struct CountedValues {
+    #[count(u8)]
+    numbers: Vec<u16>,
+    #[count(u8)]
+    flags: Vec<bool>,
+    #[count(u8)]
+    hashes: Vec<[u8; 2]>,
+}
+
+#[test]
+fn counted_values_round_trip() {
+    let msg = CountedValues {
+        numbers: vec![1, 0x0203],
+        flags: vec![true, false],
+        hashes: vec![[4, 5]],
+    };
+    let bytes = msg.serialize().unwrap();
+    assert_eq!(bytes, [2, 1, 0, 3, 2, 2, 1, 0, 1, 4, 5]);
+    assert_eq!(msg.encoded_len(), bytes.len());
+    assert_eq!(CountedValues::deserialize(&bytes).unwrap(), msg);
+
+    // Counted bools are as strict as bool fields.
+    assert!(CountedValues::deserialize(&[0, 1, 2, 0]).is_err());
+}
+
+#[derive(Message, Debug, PartialEq)]
+struct VarintBytes {
+    #[varint]
+    bytes: [u8; 2],
+}
+
+#[test]
+fn byte_arrays_apply_number_formats() {
+    let msg = VarintBytes { bytes: [200, 1] };
+    let bytes = msg.serialize().unwrap();
+    assert_eq!(bytes, [0xC8, 0x01, 0x01]);
+    assert_eq!(msg.encoded_len(), bytes.len());
+    assert_eq!(VarintBytes::deserialize(&bytes).unwrap(), msg);
+}
+
+#[test]
+fn overlong_varints_are_rejected() {
+    let msg = Zigzags { min: 0, max: 1 };
+    assert_eq!(Zigzags::deserialize(&[0x00, 0x02]).unwrap(), msg);
+    assert!(Zigzags::deserialize(&[0x80, 0x00, 0x02]).is_err());
+    assert!(Zigzags::deserialize(&[0x00, 0x82, 0x00]).is_err());
+    assert!(Zigzags::deserialize(&[0x00, 0x82, 0x80, 0x00]).is_err());
+}
+
+#[derive(Message, Debug, PartialEq)]
+struct PartiallyKnown {
+    kind: u8,
+    #[remainder]
+    rest: Vec<u8>,
+}
+
+#[derive(Message, Debug, PartialEq)]
+#[strict]
+struct Strict {
+    id: u16,
+    position: Position,
+}
+
+#[test]
+fn remainder_captures_trailing_bytes() {
+    let msg = PartiallyKnown::deserialize(&[7, 1, 2, 3]).unwrap();
+    assert_eq!(msg.rest, [1, 2, 3]);
+    assert_eq!(msg.serialize().unwrap(), [7, 1, 2, 3]);
+    assert!(PartiallyKnown::deserialize(&[7]).unwrap().rest.is_empty());
+}
+
+#[test]
+fn strict_rejects_trailing_bytes() {
+    let msg = Strict {
+        id: 1,
+        position: Position { x: 2, y: 3 },
+    };
+    let mut bytes = msg.serialize().unwrap();
+    assert_eq!(Strict::deserialize(&bytes).unwrap(), msg);
+
+    bytes.push(0);
+    assert!(Strict::deserialize(&bytes).is_err());
+    // Nested decoding leaves trailing bytes to the caller.
+    let mut rdr = std::io::Cursor::new(&bytes[..]);
+    assert_eq!(Strict::deserialize_from(&mut rdr).unwrap(), msg);
+    assert_eq!(rdr.position() as usize, bytes.len() - 1);
+}
+
+#[derive(Debug, PartialEq)]
+struct TrainerId(u32);
+
+impl Field<'_> for TrainerId {
+    fn encode(&self, writer: &mut impl std::io::Write) -> std::io::Result<()> {
+        writer.write_all(&self.0.to_be_bytes())
+    }
+
+    fn decode(rdr: &mut std::io::Cursor<&[u8]>) -> std::io::Result<Self> {
+        let mut bytes = [0u8; 4];
+        std::io::Read::read_exact(rdr, &mut bytes)?;
+        Ok(TrainerId(u32::from_be_bytes(bytes)))
+    }
+}
+
+mod seconds {
+    pub fn encode(
+        value: &std::time::SystemTime,
+        writer: &mut impl std::io::Write,
+    ) -> std::io::Result<()> {
+        let seconds = value
+            .duration_since(std::time::UNIX_EPOCH)
+            .map_err(|err| std::io::Error::new(std::io::ErrorKind::InvalidInput, err))?
+            .as_secs();
+        writer.write_all(&seconds.to_le_bytes())
+    }
+
+    pub fn decode(rdr: &mut std::io::Cursor<&[u8]>) -> std::io::Result<std::time::SystemTime> {
+        let mut bytes = [0u8; 8];
+        std::io::Read::read_exact(rdr, &mut bytes)?;
+        Ok(std::time::UNIX_EPOCH + std::time::Duration::from_secs(u64::from_le_bytes(bytes)))
+    }
+}
+
+#[derive(Message, Debug, PartialEq)]
+struct Trade {
+    from: TrainerId,
+    #[count(u8)]
+    to: Vec<TrainerId>,
+    #[with(seconds)]
+    at: std::time::SystemTime,
+    #[optional(u8)]
+    #[with(seconds)]
+    expires: Option<std::time::SystemTime>,
+    #[with(pokemmo::message::sec1_i16)]
+    key: p256::PublicKey,
+}
+
+#[test]
+fn custom_field_types_round_trip() {
+    let key = p256::SecretKey::random(&mut OsRng).public_key();
+    let msg = Trade {
+        from: TrainerId(1),
+        to: vec![TrainerId(2)],
+        at: std::time::UNIX_EPOCH + std::time::Duration::from_secs(5),
+        expires: None,
+        key,
+    };
+    let bytes = msg.serialize().unwrap();
+    assert_eq!(bytes[..4], [0, 0, 0, 1]);
+    assert_eq!(bytes[4..9], [1, 0, 0, 0, 2]);
+    assert_eq!(bytes[9..17], 5u64.to_le_bytes());
+    assert_eq!(bytes[17], 0);
+    assert_eq!(bytes[18..20], 65i16.to_le_bytes());
+    assert_eq!(bytes[20..], *key.to_sec1_bytes());
+    assert_eq!(Trade::deserialize(&bytes).unwrap(), msg);
+}
+
+#[test]
+fn public_keys_must_be_uncompressed() {
+    use p256::elliptic_curve::sec1::ToEncodedPoint;
+
+    let key = p256::SecretKey::random(&mut OsRng).public_key();
+    let compressed = key.to_encoded_point(true);
+    let mut bytes = (compressed.len() as i16).to_le_bytes().to_vec();
+    bytes.extend_from_slice(compressed.as_bytes());
+    let Err(err) = ClientReady::deserialize(&bytes) else {
+        panic!("compressed key was accepted");
+    };
+    let key_error = err.get_ref().and_then(|e| e.downcast_ref::<MalformedPublicKey>());
+    assert_eq!(key_error, Some(&MalformedPublicKey));
+
+    let mut bytes = ClientReady::new(key).serialize().unwrap();
+    assert_eq!(ClientReady::deserialize(&bytes).unwrap().public_key(), key);
+    bytes[3] ^= 0xFF;
+    assert!(ClientReady::deserialize(&bytes).is_err());
+}
+
+fn assert_encoded_len(msg: &impl MessageOwned) {
+    let bytes = msg.serialize().unwrap();
+    assert_eq!(msg.encoded_len(), bytes.len());
+
+    let mut appended = vec![0xAA];
+    msg.serialize_into(&mut appended).unwrap();
+    assert_eq!(appended[1..], bytes);
+}
+
+#[test]
+fn encoded_len_matches_serialized_length() {
+    assert_encoded_len(&Strings {
+        utf8: "Pokémon".to_string(),
+        utf8_chars: "Pokémon 🎮".to_string(),
+        utf16: "Pokémon 🎮".to_string(),
+        utf16_bytes: String::new(),
+    });
+    assert_encoded_len(&Route {
+        waypoints: vec![Position { x: 1, y: 2 }],
+        warps: vec![],
+    });
+    assert_encoded_len(&Spawn {
+        flags: 0x1,
+        position: Some(Position { x: 1, y: 2 }),
+        nickname: Some("Pika".to_string()),
+        level: Some(5),
+    });
+    assert_encoded_len(&LoginResult::Banned("Cheating".to_string(), 3600));
+    assert_encoded_len(&LoginResult::Maintenance);
+    assert_encoded_len(&LoginResult::Other {
+        tag: 9,
+        data: vec![1, 2, 3],
+    });
+    for (experience, small, delta) in [(0, 0, 0), (127, 1, -64), (u64::MAX, i8::MIN, i32::MIN)] {
+        assert_encoded_len(&Formats {
+            id: 1,
+            speed: 1.5,
+            experience,
+            small,
+            delta,
+            stats: [1, 2],
+            money: Some(128),
+            name: "Ash".repeat(50),
+            path: vec![Position { x: 1, y: 2 }],
+        });
+    }
+    assert_encoded_len(&Zigzags {
+        min: i64::MIN,
+        max: i16::MAX,
+    });
+    assert_encoded_len(&VarintBytes { bytes: [200, 1] });
+    assert_encoded_len(&PartiallyKnown {
+        kind: 7,
+        rest: vec![1, 2, 3],
+    });
+    assert_encoded_len(&Trade {
+        from: TrainerId(1),
+        to: vec![TrainerId(2), TrainerId(3)],
+        at: std::time::UNIX_EPOCH,
+        expires: Some(std::time::UNIX_EPOCH),
+        key: p256::SecretKey::random(&mut OsRng).public_key(),
+    });
+}
+
+#[test]
+fn codec_encodes_with_exact_length() {
+    use pokemmo::codec::{Codec, Login};
+
+    let key = p256::SecretKey::random(&mut OsRng).public_key();
+    let msg = Login::ClientReady(ClientReady::new(key));
+    let bytes = msg.encode().unwrap();
+    assert_eq!(msg.encoded_len(), bytes.len());
+    assert_eq!(bytes.capacity(), bytes.len());
+    assert_eq!(bytes[1..], ClientReady::new(key).serialize().unwrap());
+
+    let unknown = Login::Unknown {
+        opcode: -1,
+        data: vec![1, 2],
+    };
+    assert_eq!(unknown.encode().unwrap(), [0xFF, 1, 2]);
+    assert_eq!(unknown.encoded_len(), 3);
+}
+
+#[derive(Message, Debug, PartialEq)]
+struct Chat<'a> {
+    #[prefixed(u8)]
+    channel: &'a str,
+    #[prefixed(i16, utf8, chars)]
+    text: &'a str,
+    #[optional(u8)]
+    #[prefixed(u16, varint)]
+    attachment: Option<&'a [u8]>,
+    #[count(u8)]
+    mentions: Vec<Mention<'a>>,
+    #[remainder]
+    extra: &'a [u8],
+}
+
+#[derive(Message, Debug, PartialEq)]
+#[repr(u8)]
+#[tag(u8)]
+enum Mention<'a> {
+    Trainer(#[prefixed(u8)] &'a str) = 1,
+    #[fallback]
+    Other { tag: u8, data: &'a [u8] },
+}
+
+fn points_into(bytes: &[u8], view: &[u8]) -> bool {
+    bytes.as_ptr_range().contains(&view.as_ptr())
+}
+
+#[test]
+fn borrowed_fields_point_into_the_input() {
+    let msg = Chat {
+        channel: "trade",
+        text: "Pokémon 🎮",
+        attachment: Some(&[1, 2, 3]),
+        mentions: vec![Mention::Trainer("Ash")],
+        extra: &[9, 9],
+    };
+    let bytes = msg.serialize().unwrap();
+    assert_eq!(msg.encoded_len(), bytes.len());
+
+    let decoded = Chat::deserialize(&bytes).unwrap();
+    assert_eq!(decoded, msg);
+    assert!(points_into(&bytes, decoded.channel.as_bytes()));
+    assert!(points_into(&bytes, decoded.text.as_bytes()));
+    assert!(points_into(&bytes, decoded.attachment.unwrap()));
+    assert!(points_into(&bytes, decoded.extra));
+    let Mention::Trainer(name) = decoded.mentions[0] else {
+        panic!("wrong mention");
+    };
+    assert!(points_into(&bytes, name.as_bytes()));
+
+    let other = Mention::deserialize(&[7, 1, 2]).unwrap();
+    assert_eq!(other, Mention::Other { tag: 7, data: &[1, 2] });
+}
+
+#[test]
+fn borrowed_fields_reject_invalid_data() {
+    // Prefix longer than the input.
+    assert!(Chat::deserialize(&[6, b't', b'r']).is_err());
+    // Invalid UTF-8.
+    assert!(Chat::deserialize(&[1, 0xFF, 0, 0, 0, 0]).is_err());
+    // Input ends inside a counted character.
+    assert!(Chat::deserialize(&[0, 2, 0, b'a']).is_err());
+    assert!(Chat::deserialize(&[0, 1, 0, 0xC3]).is_err());
+}
+
+#[derive(Message, Debug, PartialEq)]
+struct Paged<T> {
+    page: u16,
+    #[count(u8)]
+    items: Vec<T>,
+}
+
+#[derive(Message, Debug, PartialEq)]
+struct Signed<T, const N: usize>
+where
+    T: std::fmt::Debug,
+{
+    payload: T,
+    signature: [u8; N],
+}
+
+#[derive(Message, Debug, PartialEq)]
+#[repr(u8)]
+#[tag(u8)]
+enum Reply<T> {
+    Done(T) = 0,
+    Failed(#[prefixed(u8)] String) = 1,
+}
+
+#[test]
+fn generic_messages_round_trip() {
+    let msg = Signed {
+        payload: Paged {
+            page: 2,
+            items: vec![Position { x: 1, y: 2 }],
+        },
+        signature: [7; 4],
+    };
+    let bytes = msg.serialize().unwrap();
+    assert_eq!(
+        bytes,
+        [&[2, 0, 1, 1, 0, 0, 0, 2, 0, 0, 0][..], &[7; 4]].concat()
+    );
+    assert_eq!(msg.encoded_len(), bytes.len());
+    assert_eq!(Signed::deserialize(&bytes).unwrap(), msg);
+
+    let reply: Reply<Position> = Reply::Done(Position { x: 3, y: 4 });
+    let bytes = reply.serialize().unwrap();
+    assert_eq!(Reply::deserialize(&bytes).unwrap(), reply);
+    let failed = Reply::<Position>::Failed("busy".to_string());
+    assert_eq!(
+        Reply::deserialize(&failed.serialize().unwrap()).unwrap(),
+        failed
+    );
+}
+
+#[test]
+fn generic_messages_can_borrow() {
+    let msg = Paged {
+        page: 0,
+        items: vec![Mention::Trainer("Ash"), Mention::Trainer("Misty")],
+    };
+    let bytes = msg.serialize().unwrap();
+    let decoded = Paged::<Mention>::deserialize(&bytes).unwrap();
+    assert_eq!(decoded, msg);
+    let Mention::Trainer(name) = decoded.items[1] else {
+        panic!("wrong mention");
+    };
+    assert!(points_into(&bytes, name.as_bytes()));
+}
+
+#[derive(Message, Debug, PartialEq)]
+#[strict]
+struct Reserved {
+    data: u8,
+    rdr: u16,
+    #[optional(u8)]
+    flag: Option<u8>,
+    #[present_if(data != 0)]
+    present: Option<u16>,
+    #[prefixed(u8)]
+    size: String,
+    #[count(u8, varint)]
+    value: Vec<Position>,
+    #[remainder]
+    tag: Vec<u8>,
+}
+
+#[derive(Message, Debug, PartialEq)]
+#[repr(u8)]
+#[tag(u8)]
+enum ReservedVariants {
+    Record {
+        tag: u16,
+        data: u8,
+        #[optional(bool)]
+        flag: Option<u8>,
+    } = 0x01,
+    #[fallback]
+    Other { tag: u8, data: Vec<u8> },
+}
+
+#[test]
+fn fields_may_use_generated_names() {
+    let msg = Reserved {
+        data: 1,
+        rdr: 2,
+        flag: Some(3),
+        present: Some(4),
+        size: "Ash".to_string(),
+        value: vec![Position { x: 5, y: 6 }],
+        tag: vec![7, 8],
+    };
+    let bytes = msg.serialize().unwrap();
+    assert_eq!(msg.encoded_len(), bytes.len());
+    assert_eq!(Reserved::deserialize(&bytes).unwrap(), msg);
+
+    let record = ReservedVariants::Record {
+        tag: 9,
+        data: 10,
+        flag: None,
+    };
+    let bytes = record.serialize().unwrap();
+    assert_eq!(bytes, [0x01, 9, 0, 10, 0]);
+    assert_eq!(ReservedVariants::deserialize(&bytes).unwrap(), record);
+    assert_eq!(
+        ReservedVariants::deserialize(&[0x02, 1]).unwrap(),
+        ReservedVariants::Other {
+            tag: 2,
+            data: vec![1]
+        }
+    );
+}
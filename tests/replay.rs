@@ -0,0 +1,103 @@
+mod common;
+
+use common::{PRIMARY_OBFUSCATION_VALUE, SECONDARY_OBFUSCATION_VALUE};
+use p256::ecdsa::SigningKey;
+use p256::elliptic_curve::rand_core::OsRng;
+use pokemmo::{
+    codec::Login,
+    context::WithContext,
+    handshake::{self, HandshakeError, ServerConfig},
+    identity::ServerIdentity,
+    message::{Checksum, ClientHello},
+    replay::{InMemoryReplayCache, ReplayCache},
+};
+use std::sync::Arc;
+use std::time::{Duration, SystemTime};
+
+#[test]
+fn in_memory_cache_rejects_duplicates() {
+    let cache = InMemoryReplayCache::new(16, Duration::from_secs(60));
+    let timestamp = SystemTime::now();
+
+    assert!(cache.insert(1, timestamp));
+    assert!(!cache.insert(1, timestamp));
+    assert!(cache.insert(2, timestamp));
+    assert!(cache.insert(1, timestamp + Duration::from_millis(1)));
+    assert_eq!(cache.len(), 3);
+}
+
+#[test]
+fn full_cache_rejects_new_entries() {
+    let cache = InMemoryReplayCache::new(2, Duration::from_secs(60));
+    let timestamp = SystemTime::now();
+
+    assert!(cache.insert(1, timestamp));
+    assert!(cache.insert(2, timestamp));
+    assert!(!cache.insert(3, timestamp));
+    assert_eq!(cache.len(), 2);
+    // Live entries are not forgotten to make room.
+    assert!(!cache.insert(1, timestamp));
+    assert!(!cache.insert(2, timestamp));
+}
+
+#[test]
+fn full_cache_accepts_entries_once_expired() {
+    let cache = InMemoryReplayCache::new(1, Duration::from_millis(20));
+    let timestamp = SystemTime::now();
+
+    assert!(cache.insert(1, timestamp));
+    assert!(!cache.insert(2, timestamp));
+    std::thread::sleep(Duration::from_millis(40));
+    assert!(cache.insert(2, timestamp));
+}
+
+#[test]
+fn in_memory_cache_forgets_expired_entries() {
+    let cache = InMemoryReplayCache::new(16, Duration::from_millis(20));
+    let timestamp = SystemTime::now();
+
+    assert!(cache.insert(1, timestamp));
+    std::thread::sleep(Duration::from_millis(40));
+    assert!(cache.insert(1, timestamp));
+}
+
+#[test]
+fn server_handshake_rejects_replayed_client_hello() {
+    let cache = Arc::new(InMemoryReplayCache::new(16, Duration::from_secs(60)));
+    let mut config = ServerConfig::new(
+        PRIMARY_OBFUSCATION_VALUE,
+        SECONDARY_OBFUSCATION_VALUE,
+        ServerIdentity::new(SigningKey::random(&mut OsRng)),
+        Checksum::None,
+        OsRng,
+    )
+    .with_replay_cache(cache.clone());
+
+    let mut captured = std::io::Cursor::new(Vec::new()).with_context::<Login>();
+    let client_hello = ClientHello::new(
+        42,
+        SystemTime::now(),
+        PRIMARY_OBFUSCATION_VALUE,
+        SECONDARY_OBFUSCATION_VALUE,
+    )
+    .unwrap();
+    captured.write_message(client_hello).unwrap();
+    let captured = captured.get_ref().clone();
+
+    // The first connection gets past ClientHello and only fails waiting for ClientReady.
+    let stream = std::io::Cursor::new(captured.clone()).with_context::<Login>();
+    let Err(err) = handshake::server(stream, &mut config) else {
+        panic!("handshake completed without ClientReady");
+    };
+    assert_eq!(err.kind(), std::io::ErrorKind::UnexpectedEof);
+
+    let stream = std::io::Cursor::new(captured).with_context::<Login>();
+    let Err(err) = handshake::server(stream, &mut config) else {
+        panic!("replayed ClientHello was accepted");
+    };
+    let handshake_error = err
+        .get_ref()
+        .and_then(|e| e.downcast_ref::<HandshakeError>());
+    assert_eq!(handshake_error, Some(&HandshakeError::Replayed));
+    assert_eq!(cache.len(), 1);
+}
@@ -0,0 +1,38 @@
+mod common;
+
+use common::session_pair;
+use pokemmo::session::{DirectionKeys, Role};
+
+fn same_keys(a: &DirectionKeys, b: &DirectionKeys) -> bool {
+    a.encryption_key() == b.encryption_key()
+        && a.iv() == b.iv()
+        && a.integrity_key() == b.integrity_key()
+}
+
+#[test]
+fn peers_derive_crossed_keys() {
+    let (client, server) = session_pair();
+    assert_eq!(client.role(), Role::Client);
+    assert_eq!(server.role(), Role::Server);
+
+    assert!(same_keys(client.outbound(), server.inbound()));
+    assert!(same_keys(client.inbound(), server.outbound()));
+    assert!(same_keys(
+        client.client_to_server(),
+        server.client_to_server()
+    ));
+    assert!(same_keys(
+        client.server_to_client(),
+        server.server_to_client()
+    ));
+}
+
+#[test]
+fn directions_use_distinct_keys() {
+    let (client, _) = session_pair();
+    let outbound = client.outbound();
+    let inbound = client.inbound();
+    assert_ne!(outbound.encryption_key(), inbound.encryption_key());
+    assert_ne!(outbound.iv(), inbound.iv());
+    assert_ne!(outbound.integrity_key(), inbound.integrity_key());
+}
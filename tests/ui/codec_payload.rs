@@ -0,0 +1,6 @@
+#[pokemmo_macros::codec]
+enum TwoFields {
+    Hello(u8, u8) = 0x00u8,
+}
+
+fn main() {}
@@ -0,0 +1,11 @@
+#[pokemmo_macros::codec]
+struct NotAnEnum {
+    opcode: u8,
+}
+
+#[pokemmo_macros::codec(krate = pokemmo)]
+enum UnknownOption {
+    Unknown { opcode: u8, data: Vec<u8> },
+}
+
+fn main() {}
@@ -0,0 +1,6 @@
+#[pokemmo_macros::codec]
+enum WideUnknown {
+    Unknown { opcode: u16, data: Vec<u8> },
+}
+
+fn main() {}
@@ -0,0 +1,6 @@
+#[pokemmo_macros::codec]
+enum MissingOpcode {
+    Hello(u8),
+}
+
+fn main() {}
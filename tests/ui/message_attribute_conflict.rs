@@ -0,0 +1,27 @@
+#[derive(pokemmo::Message)]
+struct PrefixedAndCount {
+    #[prefixed(u8)]
+    #[count(u8)]
+    payload: Vec<u8>,
+}
+
+#[derive(pokemmo::Message)]
+struct BigEndianBool {
+    #[be]
+    visible: bool,
+}
+
+#[derive(pokemmo::Message)]
+struct UnsignedZigzag {
+    #[zigzag]
+    delta: u32,
+}
+
+#[derive(pokemmo::Message)]
+struct EarlyRemainder {
+    #[remainder]
+    rest: Vec<u8>,
+    id: u8,
+}
+
+fn main() {}
@@ -0,0 +1,22 @@
+#[derive(pokemmo::Message)]
+enum MissingTag {
+    A = 1,
+}
+
+#[derive(pokemmo::Message)]
+#[tag(u8)]
+enum MissingDiscriminant {
+    A = 1,
+    B,
+}
+
+#[derive(pokemmo::Message)]
+#[repr(u8)]
+#[tag(u8)]
+enum BadFallback {
+    A(u8) = 1,
+    #[fallback]
+    Other(u8),
+}
+
+fn main() {}
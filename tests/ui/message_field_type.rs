@@ -0,0 +1,28 @@
+#[derive(pokemmo::Message)]
+struct Reference {
+    name: &'static str,
+}
+
+#[derive(pokemmo::Message)]
+struct PointerSized {
+    length: usize,
+}
+
+#[derive(pokemmo::Message)]
+struct Tuple {
+    position: (i32, i32),
+}
+
+#[derive(pokemmo::Message)]
+struct PrefixedNumber {
+    #[prefixed(u8)]
+    level: u16,
+}
+
+#[derive(pokemmo::Message)]
+struct BorrowedUtf16<'a> {
+    #[prefixed(i16, utf16)]
+    name: &'a str,
+}
+
+fn main() {}
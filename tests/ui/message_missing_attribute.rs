@@ -0,0 +1,16 @@
+#[derive(pokemmo::Message)]
+struct MissingPrefix {
+    payload: Vec<u8>,
+}
+
+#[derive(pokemmo::Message)]
+struct MissingStringPrefix {
+    name: String,
+}
+
+#[derive(pokemmo::Message)]
+struct MissingPresence {
+    level: Option<u16>,
+}
+
+fn main() {}
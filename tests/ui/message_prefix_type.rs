@@ -0,0 +1,19 @@
+#[derive(pokemmo::Message)]
+struct FloatPrefix {
+    #[prefixed(f32)]
+    payload: Vec<u8>,
+}
+
+#[derive(pokemmo::Message)]
+struct StringCount {
+    #[count(String)]
+    elements: Vec<u8>,
+}
+
+#[derive(pokemmo::Message)]
+struct UnknownOption {
+    #[prefixed(u16, utf32)]
+    name: String,
+}
+
+fn main() {}
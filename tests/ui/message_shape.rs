@@ -0,0 +1,18 @@
+#[derive(pokemmo::Message)]
+struct Tuple(u8, u16);
+
+#[derive(pokemmo::Message)]
+struct Unit;
+
+#[derive(pokemmo::Message)]
+union Union {
+    byte: u8,
+}
+
+#[derive(pokemmo::Message)]
+#[message(path = pokemmo)]
+struct UnknownOption {
+    byte: u8,
+}
+
+fn main() {}
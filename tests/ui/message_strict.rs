@@ -0,0 +1,13 @@
+#[derive(pokemmo::Message)]
+#[strict(trailing)]
+struct ListArguments {
+    byte: u8,
+}
+
+#[derive(pokemmo::Message)]
+#[strict = 1]
+struct ValueArgument {
+    byte: u8,
+}
+
+fn main() {}